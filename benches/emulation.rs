@@ -0,0 +1,72 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use sapxemu::{alu::Flags, computer::Computer, control::decoder::Decoder, examples::find, interpreter::Interpreter, pc::ADDRESS_SIZE};
+
+// Shipped example programs that halt
+const PROGRAMS: [&str; 3] = ["count_up", "fibonacci", "multiply"];
+
+fn run_to_halt(program: &[u8]) -> Computer {
+    let mut computer = Computer::new(program.to_vec());
+    let result = computer.run_until(|_| false, usize::MAX);
+    assert!(result.halted, "program did not halt");
+    computer
+}
+
+fn programs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("micro-steps");
+    for name in PROGRAMS {
+        let program = find(name).unwrap().image;
+        // Each micro-step is one clock cycle
+        group.throughput(Throughput::Elements(run_to_halt(program).cycles as u64));
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || Computer::new(program.to_vec()),
+                |mut computer| computer.run_until(|_| false, usize::MAX),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn interpreter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("interpreter");
+    for name in PROGRAMS {
+        let program = find(name).unwrap().image;
+        group.throughput(Throughput::Elements(run_to_halt(program).cycles as u64));
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || Interpreter::new(program.to_vec()),
+                |mut interpreter| interpreter.run(usize::MAX),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn decoder(c: &mut Criterion) {
+    let decoder = Decoder::new();
+    let flags = Flags { carry: true, zero: true };
+    let mut group = c.benchmark_group("decoder");
+    group.throughput(Throughput::Elements(256));
+    group.bench_function("decode_instruction", |b| {
+        b.iter(|| {
+            for word in 0..=0xFF {
+                black_box(decoder.decode_instruction(black_box(word) >> ADDRESS_SIZE, flags));
+            }
+        })
+    });
+    group.bench_function("disassemble", |b| {
+        b.iter(|| {
+            for word in 0..=0xFF {
+                black_box(decoder.disassemble(black_box(word)));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, programs, interpreter, decoder);
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+use std::{cell::RefCell, rc::Rc};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ratatui::{backend::TestBackend, Terminal};
+use sapxemu::{clock::Clock, computer::Computer, config::CLOCK_FREQUENCY, display::{renderer::{render, View}, theme::Theme, waveform::Waveform}, events::bindings::KeyBindings};
+
+fn frame(c: &mut Criterion) {
+    let clock = Rc::new(RefCell::new(Clock::new(CLOCK_FREQUENCY)));
+    let mut computer = Computer::new(vec![0x1E, 0x2F, 0x50, 0xF0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 4]);
+    let mut waveform = Waveform::new(64);
+    // Mid-program, so the waveform and output log have content
+    for _ in 0..8 {
+        computer.tick_half();
+        waveform.record(&computer, true);
+        computer.tick_half();
+    }
+    let view = View::new(KeyBindings::default().describe(), Theme::default_theme());
+
+    let mut group = c.benchmark_group("render");
+    for (name, width, height) in [("full", 160, 50), ("compact", 80, 24)] {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut regions = Vec::new();
+                terminal.draw(|f| render(f, &clock, &computer, &waveform, &view, &mut regions)).unwrap();
+                regions
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, frame);
+criterion_main!(benches);
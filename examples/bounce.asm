@@ -0,0 +1,15 @@
+; Bouncing counter
+; Counts up from 0 to 255, back down to 0 and up again, forever.
+
+0:  OUT
+1:  ADD 15      ; A = A + 1
+2:  JC 4        ; wrapped around past 255
+3:  JMP 0
+4:  SUB 14      ; A = 0 - 2 = 254
+5:  OUT
+6:  JZ 1        ; back at 0, count up
+7:  SUB 15      ; A = A - 1
+8:  JMP 5
+
+14: 2           ; turn
+15: 1           ; step
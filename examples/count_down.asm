@@ -0,0 +1,10 @@
+; Count down
+; Outputs 255, 254, ... 0 and halts when the subtraction gives zero.
+
+0:  SUB 15      ; A = A - 1, A starts at 0 so the first value is 255
+1:  OUT
+2:  JZ 4        ; reached 0
+3:  JMP 0
+4:  HLT
+
+15: 1           ; step
@@ -0,0 +1,10 @@
+; Count up
+; Outputs 0, 1, 2, ... 255 and halts when the addition carries past 255.
+
+0:  OUT         ; A starts at 0
+1:  ADD 15      ; A = A + 1
+2:  JC 4        ; wrapped around to 0
+3:  JMP 0
+4:  HLT
+
+15: 1           ; step
@@ -0,0 +1,18 @@
+; Fibonacci
+; Outputs 1, 2, 3, 5, 8 ... 233 and halts when the next term no longer fits in 8 bits.
+
+0:  LDA 14      ; A = x
+1:  ADD 15      ; A = x + y
+2:  JC 10       ; overflow, done
+3:  OUT
+4:  STA 13      ; next = x + y
+5:  LDA 15
+6:  STA 14      ; x = y
+7:  LDA 13
+8:  STA 15      ; y = next
+9:  JMP 0
+10: HLT
+
+13: 0           ; next term
+14: 0           ; x
+15: 1           ; y
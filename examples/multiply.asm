@@ -0,0 +1,20 @@
+; Multiplication
+; Multiplies 17 by 15 with repeated addition, outputs 255 and halts.
+; Change the factors at addresses 14 and 15, the multiplier must not be 0.
+
+0:  LDA 13
+1:  ADD 14
+2:  STA 13      ; product = product + multiplicand
+3:  LDA 15
+4:  SUB 12
+5:  STA 15      ; multiplier = multiplier - 1
+6:  JZ 8
+7:  JMP 0
+8:  LDA 13
+9:  OUT
+10: HLT
+
+12: 1           ; one
+13: 0           ; product
+14: 17          ; multiplicand
+15: 15          ; multiplier
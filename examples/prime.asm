@@ -0,0 +1,22 @@
+; Prime test by subtraction
+; Tries every divisor from N - 1 down to 2, dividing by repeated subtraction.
+; Outputs N if it is prime, 0 as soon as a divisor leaves no remainder, then halts.
+; Change N at address 13 and set the divisor at address 14 to the same value, N must be at least 2.
+
+0:  LDA 14
+1:  SUB 15
+2:  STA 14      ; divisor = divisor - 1
+3:  SUB 15
+4:  JZ 10       ; divisor is 1, no divisor found
+5:  LDA 13      ; A = N
+6:  SUB 14      ; A = A - divisor
+7:  JZ 11       ; no remainder, output 0
+8:  JC 6        ; no borrow, keep subtracting
+9:  JMP 0       ; remainder left, try the next divisor
+10: LDA 13
+11: OUT
+12: HLT
+
+13: 97          ; N
+14: 97          ; divisor
+15: 1           ; one
@@ -1,54 +1,96 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use crate::{bitvecutils::{mask, Word}, config::WORD_SIZE};
 
-use bit_vec::BitVec;
-
-use crate::{bus::Bus, control::control::ControlLine, link::Link, memory::register::RWRegister};
-
-fn sum_bitvecs(a: &BitVec, b: &BitVec) -> BitVec {
-    let mut result = BitVec::new();
-    let mut carry = false;  // This will hold the carry bit during the addition
-    
-    let max_len = std::cmp::max(a.len(), b.len());  // Find the maximum length of the two BitVecs
-    for i in 0..max_len {
-        let bit_a = a.get(i).unwrap_or(false);  // Get the bit at position i (defaulting to false if out of bounds)
-        let bit_b = b.get(i).unwrap_or(false);  // Get the bit at position i (defaulting to false if out of bounds)
-
-        // Perform the addition with carry
-        let sum = bit_a as u8 + bit_b as u8 + carry as u8;
-        result.push(sum & 1 == 1);  // Store the least significant bit of the sum
-        carry = sum > 1;  // If sum > 1, there is a carry
-    }
+// Output of the adder, as seen on the ALU pins
+pub struct ALUResult {
+    pub value: Word,
+    pub carry: bool,
+    pub zero: bool,
+}
 
-    if carry {
-        result.push(true);  // If there's a carry left after the last bit, push a '1' to the result
+/// Adder of the ALU, subtraction adds the two's complement of B.
+pub fn add(a: Word, b: Word, subtract: bool) -> ALUResult {
+    let (a, b) = (a as u32, b as u32);
+    let sum = if subtract {
+        a + (!b & mask(WORD_SIZE) as u32) + 1
+    } else {
+        a + b
+    };
+    let value = sum as Word & mask(WORD_SIZE);
+    ALUResult {
+        value,
+        carry: sum >> WORD_SIZE & 1 == 1,
+        zero: value == 0,
     }
+}
 
-    result
+// Flags Register, latched from the adder on FI for the conditional jumps
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Flags {
+    pub carry: bool,
+    pub zero: bool,
 }
 
 pub struct ALU {
-    control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,
-    bus: Rc<RefCell<Bus>>,
-    reg_a: Rc<RefCell<RWRegister>>,
-    reg_b: Rc<RefCell<RWRegister>>,
+    pub flags: Flags,
+}
+
+impl Default for ALU {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ALU {
-    pub fn new(control_links: HashMap<ControlLine, Rc<RefCell<Link>>>, bus: Rc<RefCell<Bus>>, reg_a: Rc<RefCell<RWRegister>>, reg_b: Rc<RefCell<RWRegister>>) -> Self {
+    pub fn new() -> Self {
         Self {
-            control_links,
-            bus,
-            reg_a,
-            reg_b
+            flags: Flags::default(),
         }
     }
 
-    pub fn add(&self) {
-        self.bus.borrow_mut().write(&sum_bitvecs(&self.reg_a.borrow().read(), &self.reg_b.borrow().read()));
+    /// Latches the carry and zero of the adder, on FI.
+    pub fn latch_flags(&mut self, result: &ALUResult) {
+        self.flags = Flags {
+            carry: result.carry,
+            zero: result.zero,
+        };
     }
+}
 
-    pub fn sub(&self) {
-        self.bus.borrow_mut().write(&sum_bitvecs(&self.reg_a.borrow().read(), &self.reg_b.borrow().read()));
-    }
+// ALU as drawn by the interface, the adder output for the current A, B and SU
+pub struct ALUStatus {
+    pub result: ALUResult,
+    pub subtracting: bool,
+    pub flags: Flags,
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_with_carry() {
+        let result = add(200, 100, false);
+        assert_eq!(result.value, 44);
+        assert!(result.carry);
+        assert!(!result.zero);
+        assert!(!add(3, 4, false).carry);
+    }
+
+    #[test]
+    fn subtracts_in_twos_complement() {
+        let result = add(30, 12, true);
+        assert_eq!(result.value, 18);
+        // Carry set means no borrow
+        assert!(result.carry);
+        let result = add(3, 4, true);
+        assert_eq!(result.value, 0xFF);
+        assert!(!result.carry);
+    }
+
+    #[test]
+    fn zero_flag_follows_the_result() {
+        assert!(add(5, 5, true).zero);
+        assert!(add(128, 128, false).zero);
+        assert!(!add(0, 1, false).zero);
+    }
+}
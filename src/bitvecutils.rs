@@ -1,97 +1,131 @@
-use bit_vec::BitVec;
+use crate::config::WORD_SIZE;
 
-pub fn get_bitvec_subset(bitvec: &BitVec, position: usize, size: usize) -> BitVec {
-    // Ensure the position and size are valid
-    if position + size > bitvec.len() {
-        return BitVec::from_elem(size, false);
-    }
+/// Contents of a register, bus or RAM cell, only the low `width` bits are used.
+pub type Word = u16;
 
-    // Create a new BitVec to store the subset
-    let mut subset = BitVec::new();
+const _: () = assert!(WORD_SIZE <= Word::BITS as usize, "WORD_SIZE does not fit in a Word");
 
-    // Iterate over the range from position to position + size
-    for i in position..position + size {
-        // Get the bit at position `i` in the original BitVec and push it to the subset
-        subset.push(bitvec.get(i).unwrap_or(false));
+/// Keeps the low `width` bits of a word.
+pub const fn mask(width: usize) -> Word {
+    if width >= Word::BITS as usize {
+        Word::MAX
+    } else {
+        (1 << width) - 1
     }
-
-    subset
 }
 
-pub fn bitvec_to_usize(bitvec: &bit_vec::BitVec) -> usize {
-    let mut value = 0usize;
+/// Extracts `size` bits starting at bit `position`.
+pub fn get_bits(word: Word, position: usize, size: usize) -> Word {
+    if position >= Word::BITS as usize {
+        return 0;
+    }
+    (word >> position) & mask(size)
+}
 
-    // Iterate over the bits in the BitVec and ensure correct alignment (Little Endian)
-    for (index, bit) in bitvec.iter().enumerate() {
-        // Shift the bit by its index position (bit position).
-        if bit {
-            value |= 1 << index; // Set the bit at position `index` if `bit` is `true`.
-        }
+/// Parses "0x" hexadecimal, "0b" binary or decimal text, rejecting values wider than `size` bits.
+pub fn parse_word(text: &str, size: usize) -> Option<Word> {
+    let text = text.trim().to_lowercase().replace('_', "");
+    let value = if let Some(hex) = text.strip_prefix("0x") {
+        usize::from_str_radix(hex, 16).ok()?
+    } else if let Some(bin) = text.strip_prefix("0b") {
+        usize::from_str_radix(bin, 2).ok()?
+    } else {
+        text.parse::<usize>().ok()?
+    };
+    if value > mask(size) as usize {
+        return None;
     }
+    Some(value as Word)
+}
 
-    value
+/// Splits a ramdump into words of `width` bits, the bits of each byte are taken least significant first.
+pub fn convert_ramdump_to_words(ramdump: &[u8], width: usize, count: usize) -> Vec<Word> {
+    let bit = |index: usize| ramdump.get(index / 8).is_some_and(|byte| byte >> (index % 8) & 1 == 1);
+    (0..count)
+        .map(|address| {
+            (0..width)
+                .filter(|&i| bit(address * width + i))
+                .fold(0, |word, i| word | 1 << i)
+        })
+        .collect()
 }
 
-pub fn increment_bitset(bitvec: &mut BitVec) {
-    let mut carry = true;
-
-    // Iterate over the bits from least significant to most significant
-    for mut bit in bitvec.iter_mut() {
-        if carry {
-            // If carry is true, flip the bit and stop if it's 0
-            if *bit == false {
-                *bit = true;
-                carry = false; // No further carry needed
-                break;
-            } else {
-                *bit = false; // If bit was 1, it becomes 0 and carry continues
-            }
+/// Packs words of `width` bits back into a ramdump, the inverse of `convert_ramdump_to_words`.
+pub fn convert_words_to_ramdump(words: &[Word], width: usize) -> Vec<u8> {
+    let mut ramdump = vec![0; (words.len() * width).div_ceil(8)];
+    for (address, word) in words.iter().enumerate() {
+        for i in (0..width).filter(|&i| get_bits(*word, i, 1) == 1) {
+            let index = address * width + i;
+            ramdump[index / 8] |= 1 << (index % 8);
         }
     }
+    ramdump
+}
 
-    // If there is still a carry, reset
-    if carry {
-        *bitvec = BitVec::from_elem(bitvec.len(), false);
-    }
+pub trait BinaryDisplay {
+    fn to_bin_string(&self, width: usize) -> String;
 }
 
-pub fn reverse_bits_in_byte(byte: u8) -> u8 {
-    let mut reversed = 0u8;
-    for i in 0..8 {
-        if byte & (1 << i) != 0 {
-            reversed |= 1 << (7 - i); // Reverse bit position
-        }
+impl BinaryDisplay for Word {
+    fn to_bin_string(&self, width: usize) -> String {
+        // Most significant bit first
+        format!("{:0width$b}", self & mask(width), width = width)
     }
-    reversed
 }
 
-pub fn convert_ramdump_to_bitvec(ramdump: &Vec<u8>) -> BitVec {
-    let mut bitvec = BitVec::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Iterate through each byte in the ramdump and reverse its bits
-    for byte in ramdump {
-        let reversed_byte = reverse_bits_in_byte(*byte);
-        
-        // Now, push each bit of the reversed byte into the BitVec
-        for i in 0..8 {
-            bitvec.push((reversed_byte & (1 << (7 - i))) != 0);
-        }
+    #[test]
+    fn mask_keeps_the_low_bits() {
+        assert_eq!(mask(0), 0);
+        assert_eq!(mask(4), 0x0F);
+        assert_eq!(mask(8), 0xFF);
+        assert_eq!(mask(16), Word::MAX);
     }
 
-    bitvec
-}
+    #[test]
+    fn get_bits_extracts_a_field() {
+        assert_eq!(get_bits(0xA5, 0, 4), 0x5);
+        assert_eq!(get_bits(0xA5, 4, 4), 0xA);
+        assert_eq!(get_bits(0xA5, 16, 4), 0);
+    }
 
-pub trait BinaryDisplay {
-    fn to_bin_string(&self) -> String;
-}
+    #[test]
+    fn parse_word_accepts_every_base() {
+        assert_eq!(parse_word("0x1E", 8), Some(0x1E));
+        assert_eq!(parse_word("0b0001_1110", 8), Some(0x1E));
+        assert_eq!(parse_word(" 30 ", 8), Some(30));
+        assert_eq!(parse_word("0XFF", 8), Some(0xFF));
+    }
 
+    #[test]
+    fn parse_word_rejects_wide_or_invalid_values() {
+        assert_eq!(parse_word("256", 8), None);
+        assert_eq!(parse_word("0x10", 4), None);
+        assert_eq!(parse_word("LDA", 8), None);
+        assert_eq!(parse_word("", 8), None);
+    }
+
+    #[test]
+    fn ramdump_splits_into_words() {
+        assert_eq!(convert_ramdump_to_words(&[0x1E, 0xF0], 8, 3), vec![0x1E, 0xF0, 0]);
+        // Narrow words take the low nibble first
+        assert_eq!(convert_ramdump_to_words(&[0xA5], 4, 2), vec![0x5, 0xA]);
+    }
+
+    #[test]
+    fn words_pack_back_into_a_ramdump() {
+        assert_eq!(convert_words_to_ramdump(&[0x1E, 0xF0], 8), vec![0x1E, 0xF0]);
+        assert_eq!(convert_words_to_ramdump(&[0x5, 0xA, 0x3], 4), vec![0xA5, 0x03]);
+        // Bits above the width are dropped
+        assert_eq!(convert_words_to_ramdump(&[0x1FF], 8), vec![0xFF]);
+    }
 
-impl BinaryDisplay for BitVec {
-    fn to_bin_string(&self) -> String {
-        // Collect all bits into a string representation in correct order
-        self.iter()
-            .rev() // Reverse the order of bits before mapping
-            .map(|bit| if bit { '1' } else { '0' })
-            .collect()
+    #[test]
+    fn binary_display_pads_to_the_width() {
+        assert_eq!((0x05 as Word).to_bin_string(8), "00000101");
+        assert_eq!((0x1F as Word).to_bin_string(4), "1111");
     }
 }
@@ -1,6 +1,4 @@
-use bit_vec::BitVec;
-
-use crate::{bitvecutils::get_bitvec_subset, config::WORD_SIZE};
+use crate::{bitvecutils::{get_bits, mask, Word}, config::WORD_SIZE};
 
 #[derive(Copy, Clone, PartialEq)]
 pub enum BusSelector {
@@ -10,35 +8,61 @@ pub enum BusSelector {
 
 // Shared Bus
 pub struct Bus {
-    pub data: BitVec,
+    pub data: Word,
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Bus {
     pub fn new() -> Self {
         Self {
-            data: BitVec::from_elem(WORD_SIZE, false)
+            data: 0
         }
     }
 
-    pub fn read_part(&self, count: usize, selector: BusSelector) -> BitVec {
+    pub fn read_part(&self, count: usize, selector: BusSelector) -> Word {
         let position = match selector {
-            BusSelector::LSB => 0, // Starting from the beginning for HSB
-            BusSelector::HSB => self.data.len() - count, // Starting from the end for LSB
+            BusSelector::LSB => 0, // Starting from the least significant bit
+            BusSelector::HSB => WORD_SIZE - count, // Starting from the most significant bit
         };
-        // Create and return the subset
-        get_bitvec_subset(&self.data, position, count)
+        get_bits(self.data, position, count)
     }
 
-    pub fn read(&self) -> BitVec {
-        return self.data.clone();
+    pub fn read(&self) -> Word {
+        self.data
     }
-    pub fn write(&mut self, value: &BitVec) {
-        // Get the minimum size between the current data and the new value
-        let copy_size = self.data.len().min(value.len());
-    
-        // Copy the bits from value into self.data, respecting the fixed size
-        for i in 0..copy_size {
-            self.data.set(i, value[i]);
-        }
+
+    /// Drives the low `width` bits, the other lines keep their level.
+    pub fn write(&mut self, value: Word, width: usize) {
+        let driven = mask(width.min(WORD_SIZE));
+        self.data = (self.data & !driven) | (value & driven);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_part_selects_a_half() {
+        let mut bus = Bus::new();
+        bus.write(0xA5, WORD_SIZE);
+        assert_eq!(bus.read_part(4, BusSelector::LSB), 0x5);
+        assert_eq!(bus.read_part(4, BusSelector::HSB), 0xA);
+        assert_eq!(bus.read_part(WORD_SIZE, BusSelector::LSB), 0xA5);
+    }
+
+    #[test]
+    fn narrow_writes_keep_the_other_lines() {
+        let mut bus = Bus::new();
+        bus.write(0xA5, WORD_SIZE);
+        bus.write(0x3, 4);
+        assert_eq!(bus.read(), 0xA3);
+        bus.write(0x1FF, WORD_SIZE);
+        assert_eq!(bus.read(), 0xFF);
     }
 }
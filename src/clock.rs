@@ -2,24 +2,89 @@ use std::{
     sync::{mpsc::Sender, Arc, Mutex}, thread::{self, JoinHandle}, time::Duration
 };
 
+#[derive(Copy, Clone, PartialEq)]
+pub enum ClockMode {
+    Running,
+    Paused,
+    Halted,
+}
+
 pub struct Clock {
-    frequency: usize,                // Hertz
+    frequency: Arc<Mutex<usize>>,    // Hertz, shared with the clock thread
     running: Arc<Mutex<bool>>,       // Shared running state
     thread_handle: Option<JoinHandle<()>>, // Handle to the clock thread
+    mode: ClockMode,
+    state: bool,                     // Current clock level
+    pub cycles: usize,               // Rising edges seen
+    pub instructions: usize,         // Instructions completed
 }
 
 impl Clock {
     pub fn new(frequency: usize) -> Self {
         Self {
-            frequency,
+            frequency: Arc::new(Mutex::new(frequency)),
             running: Arc::new(Mutex::new(false)), // Initially paused
             thread_handle: None,
+            mode: ClockMode::Paused,
+            state: false,
+            cycles: 0,
+            instructions: 0,
+        }
+    }
+
+    pub fn get_frequency(&self) -> usize {
+        *self.frequency.lock().unwrap()
+    }
+
+    pub fn set_frequency(&mut self, frequency: usize) {
+        *self.frequency.lock().unwrap() = frequency.max(1);
+    }
+
+    pub fn get_mode(&self) -> ClockMode {
+        self.mode
+    }
+
+    pub fn get_state(&self) -> bool {
+        self.state
+    }
+
+    pub fn on_edge(&mut self, state: bool) {
+        self.state = state;
+        if state {
+            self.cycles += 1;
+        }
+    }
+
+    pub fn count_instruction(&mut self) {
+        self.instructions += 1;
+    }
+
+    /// Inhibits the clock, edges still generated by the thread are ignored.
+    pub fn halt(&mut self) {
+        self.mode = ClockMode::Halted;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.mode = match self.mode {
+            ClockMode::Running => ClockMode::Paused,
+            ClockMode::Paused => ClockMode::Running,
+            ClockMode::Halted => ClockMode::Halted,
+        };
+    }
+
+    /// Clears counters and the halt state, keeping the clock paused or running.
+    pub fn reset(&mut self) {
+        if self.mode == ClockMode::Halted {
+            self.mode = ClockMode::Running;
         }
+        self.state = false;
+        self.cycles = 0;
+        self.instructions = 0;
     }
 
     pub fn start(&mut self, tx: Sender<bool>) {
         let running = Arc::clone(&self.running);
-        let frequency = self.frequency;
+        let frequency = Arc::clone(&self.frequency);
 
         // If a thread is already running, do nothing
         if self.thread_handle.is_some() {
@@ -29,12 +94,12 @@ impl Clock {
 
         // Set the running flag to true
         *running.lock().unwrap() = true;
+        self.mode = ClockMode::Running;
 
         // Spawn the clock thread
         self.thread_handle = Some(thread::spawn(move || {
-            let half_interval = Duration::from_millis((1000 / frequency as u64) / 2);
-
             while *running.lock().unwrap() {
+                let half_interval = Duration::from_micros(500_000 / *frequency.lock().unwrap() as u64);
                 // Send a signal to the main thread
                 if tx.send(true).is_err() {
                     println!("Main thread disconnected. Clock thread exiting.");
@@ -54,6 +119,9 @@ impl Clock {
         // Set the running flag to false to stop the clock thread
         if let Some(handle) = self.thread_handle.take() {
             *self.running.lock().unwrap() = false;
+            if self.mode == ClockMode::Running {
+                self.mode = ClockMode::Paused;
+            }
 
             // Wait for the clock thread to finish
             handle.join().unwrap();
@@ -63,6 +131,11 @@ impl Clock {
     }
 }
 
-pub trait ClockDriven {
-    fn on_clock_high(&mut self);
-}
\ No newline at end of file
+impl Drop for Clock {
+    fn drop(&mut self) {
+        // Also reached when unwinding from a panic
+        if self.thread_handle.is_some() {
+            self.stop();
+        }
+    }
+}
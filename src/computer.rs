@@ -1,206 +1,444 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{collections::BTreeSet, fmt};
 
-use crate::alu::ALU;
-use crate::bitvecutils::convert_ramdump_to_bitvec;
+use crate::alu::{add, ALUStatus, Flags, ALU};
+use crate::bitvecutils::{mask, Word};
 use crate::bus::{Bus, BusSelector};
-use crate::clock::ClockDriven;
 use crate::config::{OPCODE_SIZE, WORD_SIZE};
-use crate::control::control::ControlLine;
+use crate::control::line::ControlLine;
 use crate::control::controller::Controller;
-use crate::link::Link;
-use crate::memory::memory::RAM;
-use crate::pc::ProgramCounter;
+use crate::link::ControlLinks;
+use crate::memory::ram::RAM;
+use crate::pc::{ProgramCounter, ADDRESS_SIZE};
 use crate::memory::register::{RORegister, RWRegister};
-use crate::control::sequencer::{self, Sequencer};
-
-fn filter_control_lines(
-    map: &HashMap<ControlLine, Rc<RefCell<Link>>>,
-    keys: &[ControlLine],
-) -> HashMap<ControlLine, Rc<RefCell<Link>>> {
-    keys.iter()
-        .filter_map(|key| map.get(key).map(|value| (key.clone(), Rc::clone(value))))
-        .collect()
+use crate::control::sequencer::Sequencer;
+
+// Registers that can be written by hand
+#[derive(Copy, Clone, PartialEq)]
+pub enum RegisterId {
+    PC,
+    MAR,
+    IR,
+    A,
+    B,
+    OUT,
+}
+
+impl fmt::Display for RegisterId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            RegisterId::PC => "Program Counter",
+            RegisterId::MAR => "Mem. Address Reg.",
+            RegisterId::IR => "Instruction Reg.",
+            RegisterId::A => "A Register",
+            RegisterId::B => "B Register",
+            RegisterId::OUT => "Output Register",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// Value latched into the Output Register
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputRecord {
+    pub cycle: usize,
+    pub value: Word,
 }
 
-fn deep_clone_hashmap(
-    map: &HashMap<ControlLine, Rc<RefCell<Link>>>,
-) -> HashMap<ControlLine, Rc<RefCell<Link>>> {
-    map.iter()
-        .map(|(key, value)| (key.clone(), Rc::clone(value)))
-        .collect()
+// Lines driving the bus, asserting more than one at once is a contention
+const BUS_OUTPUTS: [ControlLine; 6] = [ControlLine::CO, ControlLine::RO, ControlLine::IO, ControlLine::AO, ControlLine::EO, ControlLine::BO];
+
+// Outcome of a synchronous run, see Computer::tick_half
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StepResult {
+    /// Rising edges run by the call
+    pub cycles: usize,
+    /// HLT was asserted, the machine no longer ticks until reset
+    pub halted: bool,
+    /// The next instruction to fetch sits on a breakpoint
+    pub breakpoint: bool,
+    /// Several modules drove the bus during one of the cycles
+    pub contention: bool,
 }
 
+impl StepResult {
+    pub(crate) fn merge(&mut self, next: StepResult) {
+        self.cycles += next.cycles;
+        self.halted = next.halted;
+        self.breakpoint |= next.breakpoint;
+        self.contention |= next.contention;
+    }
+
+    /// Whether a run should stop here.
+    pub fn stopped(&self) -> bool {
+        self.halted || self.breakpoint
+    }
+}
+
+// Snapshot of the whole machine, see Computer::snapshot
+#[derive(Clone)]
+pub struct MachineState {
+    bus: Word,
+    pc: Word,
+    reg_a: Word,
+    reg_b: Word,
+    mar: Word,
+    ir: Word,
+    reg_out: Word,
+    flags: Flags,
+    memory: Vec<Word>,
+    sequencer: Sequencer,
+    asserted: Vec<ControlLine>,
+    cycles: usize,
+    clock_state: bool,
+    halted: bool,
+    output_log: Vec<OutputRecord>,
+}
+
+// Every module is owned by the machine and wired by control line, a clock edge takes no lock
 pub struct Computer {
-    pub control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,
-    pub bus: Rc<RefCell<Bus>>,
+    pub control_links: ControlLinks,
+    pub bus: Bus,
     pub pc: ProgramCounter,
-    pub reg_a: Rc<RefCell<RWRegister>>,
-    pub reg_b: Rc<RefCell<RWRegister>>,
-    pub alu: Rc<RefCell<ALU>>,
-    pub mar: Rc<RefCell<RORegister>>,
+    pub reg_a: RWRegister,
+    pub reg_b: RWRegister,
+    pub alu: ALU,
     pub ram: RAM,
-    pub ir: Rc<RefCell<RWRegister>>,
+    pub ir: RWRegister,
     pub reg_out: RORegister,
-    pub sequencer: Rc<RefCell<Sequencer>>,
     pub controller: Controller,
+    pub cycles: usize,
+    pub breakpoints: BTreeSet<usize>,
+    clock_state: bool,
+    halted: bool,
+    output_log: Vec<OutputRecord>,
 }
 
+// Machines are moved to worker threads and shared behind servers
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Computer>();
+};
+
 impl Computer {
     pub fn new(ramdump: Vec<u8>) -> Self {
-        let control_links = Self::init_control_links();
-        let bus = Rc::new(RefCell::new(Bus::new()));
-        let sequencer = Rc::new(RefCell::new(Sequencer::new()));
-        let reg_a = Self::init_register_a(filter_control_lines(&control_links, &[ControlLine::AI, ControlLine::AO]), Rc::clone(&bus));
-        let reg_b = Self::init_register_b(filter_control_lines(&control_links, &[ControlLine::BI, ControlLine::BO]), Rc::clone(&bus)); // Fixed typo here
-        let mar = Self::init_memory_address_register(filter_control_lines(&control_links, &[ControlLine::MI]), Rc::clone(&bus));
-
+        let mut control_links = ControlLinks::new();
+        let controller = Controller::new(&mut control_links);
         Self {
-            bus: Rc::clone(&bus),
-            pc: Self::init_program_counter(filter_control_lines(&control_links, &[ControlLine::CO, ControlLine::J, ControlLine::CE]), Rc::clone(&bus)),
-            alu: Self::init_alu(filter_control_lines(&control_links, &[ControlLine::EO, ControlLine::SU]), Rc::clone(&bus), Rc::clone(&reg_a), Rc::clone(&reg_b)),
-            reg_a,
-            reg_b,
-            ram: Self::init_ram(filter_control_lines(&control_links, &[ControlLine::RI, ControlLine::RO]), Rc::clone(&bus), Rc::clone(&mar), ramdump),
-            mar,
-            ir: Self::init_instruction_register(filter_control_lines(&control_links, &[ControlLine::II, ControlLine::IO]), Rc::clone(&bus)),
-            reg_out: Self::init_output_register(filter_control_lines(&control_links, &[ControlLine::OI]), Rc::clone(&bus)),
-            controller: Self::init_controller(deep_clone_hashmap(&control_links), Rc::clone(&sequencer)),
-            sequencer,
             control_links,
+            bus: Bus::new(),
+            pc: ProgramCounter::new(),
+            reg_a: RWRegister::new("A Register".to_string(), WORD_SIZE, BusSelector::LSB),
+            reg_b: RWRegister::new("B Register".to_string(), WORD_SIZE, BusSelector::LSB),
+            alu: ALU::new(),
+            ram: RAM::new(&ramdump),
+            ir: RWRegister::new("Instruction Reg.".to_string(), OPCODE_SIZE, BusSelector::HSB),
+            reg_out: RORegister::new("Output Register".to_string(), WORD_SIZE, BusSelector::LSB),
+            controller,
+            cycles: 0,
+            breakpoints: BTreeSet::new(),
+            clock_state: false,
+            halted: false,
+            output_log: Vec::new(),
         }
     }
 
-    fn init_control_links() -> HashMap<ControlLine, Rc<RefCell<Link>>> {
-        let mut control_links = HashMap::new();
-        for line in [
-            ControlLine::CLK, ControlLine::HLT, ControlLine::MI, ControlLine::RI, ControlLine::RO,
-            ControlLine::II, ControlLine::IO, ControlLine::AI, ControlLine::AO, ControlLine::EO,
-            ControlLine::SU, ControlLine::BI, ControlLine::BO, ControlLine::OI, ControlLine::CE,
-            ControlLine::CO, ControlLine::J, ControlLine::FI,
-        ] {
-            control_links.insert(line.clone(), Rc::new(RefCell::new(Link::new(line))));
+    /// Clears registers, bus, sequencer and control lines like the reset button, RAM is kept.
+    pub fn reset(&mut self) {
+        self.controller.release_all(&mut self.control_links);
+        self.controller.sequencer.reset();
+        self.bus.data = 0;
+        self.pc.address = 0;
+        self.reg_a.data = 0;
+        self.reg_b.data = 0;
+        self.ram.mar.data = 0;
+        self.ir.data = 0;
+        self.reg_out.data = 0;
+        self.alu = ALU::new();
+        self.cycles = 0;
+        self.clock_state = false;
+        self.halted = false;
+        self.output_log.clear();
+    }
+
+    /// Replaces RAM with a new program and resets the machine, breakpoints are cleared.
+    pub fn load_program(&mut self, ramdump: &[u8]) {
+        self.ram = RAM::new(ramdump);
+        self.breakpoints.clear();
+        self.reset();
+    }
+
+    pub fn snapshot(&self) -> MachineState {
+        MachineState {
+            bus: self.bus.read(),
+            pc: self.pc.read(),
+            reg_a: self.reg_a.read(),
+            reg_b: self.reg_b.read(),
+            mar: self.ram.mar.read(),
+            ir: self.ir.read(),
+            reg_out: self.reg_out.read(),
+            flags: self.alu.flags,
+            memory: self.ram.memory.clone(),
+            sequencer: self.controller.sequencer.clone(),
+            asserted: ControlLine::all()
+                .into_iter()
+                .filter(|line| self.control_links[line].get_state())
+                .collect(),
+            cycles: self.cycles,
+            clock_state: self.clock_state,
+            halted: self.halted,
+            output_log: self.output_log.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, state: &MachineState) {
+        self.controller.release_all(&mut self.control_links);
+        self.controller.drive_step_controls(&mut self.control_links, &state.asserted, true);
+        self.controller.sequencer = state.sequencer.clone();
+        self.bus.data = state.bus;
+        self.pc.address = state.pc;
+        self.reg_a.data = state.reg_a;
+        self.reg_b.data = state.reg_b;
+        self.ram.mar.data = state.mar;
+        self.ir.data = state.ir;
+        self.reg_out.data = state.reg_out;
+        self.alu.flags = state.flags;
+        self.ram.memory = state.memory.clone();
+        self.cycles = state.cycles;
+        self.clock_state = state.clock_state;
+        self.halted = state.halted;
+        self.output_log = state.output_log.clone();
+    }
+
+    pub fn read_register(&self, register: RegisterId) -> Word {
+        match register {
+            RegisterId::PC => self.pc.read(),
+            RegisterId::MAR => self.ram.mar.read(),
+            RegisterId::IR => self.ir.read(),
+            RegisterId::A => self.reg_a.read(),
+            RegisterId::B => self.reg_b.read(),
+            RegisterId::OUT => self.reg_out.read(),
+        }
+    }
+
+    pub fn write_register(&mut self, register: RegisterId, value: Word) {
+        let value = value & mask(self.register_width(register));
+        match register {
+            RegisterId::PC => self.pc.address = value,
+            RegisterId::MAR => self.ram.mar.data = value,
+            RegisterId::IR => self.ir.data = value,
+            RegisterId::A => self.reg_a.data = value,
+            RegisterId::B => self.reg_b.data = value,
+            RegisterId::OUT => self.reg_out.data = value,
         }
-        control_links
-    }
-
-    fn init_program_counter(
-        control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,
-        bus: Rc<RefCell<Bus>>,
-    ) -> ProgramCounter {
-        ProgramCounter::new(control_links, bus)
-    }
-
-    fn init_register_a(
-        control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,
-        bus: Rc<RefCell<Bus>>,
-    ) -> Rc<RefCell<RWRegister>> {
-        Rc::new(RefCell::new(RWRegister::new(
-            "A Register".to_string(),
-            WORD_SIZE,
-            bus,
-            BusSelector::LSB,
-            ControlLine::AI,
-            ControlLine::AO,
-            control_links,
-        )))
-    }
-
-    fn init_register_b(
-        control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,
-        bus: Rc<RefCell<Bus>>,
-    ) -> Rc<RefCell<RWRegister>> {
-        Rc::new(RefCell::new(RWRegister::new(
-            "B Register".to_string(),
-            WORD_SIZE,
-            bus,
-            BusSelector::LSB,
-            ControlLine::BI,
-            ControlLine::BO,
-            control_links,
-        )))
-    }
-
-    fn init_alu(
-        control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,
-        bus: Rc<RefCell<Bus>>,
-        reg_a: Rc<RefCell<RWRegister>>,
-        reg_b: Rc<RefCell<RWRegister>>,
-    ) -> Rc<RefCell<ALU>> {
-        Rc::new(RefCell::new(ALU::new(control_links, bus, reg_a, reg_b)))
-    }
-
-    fn init_memory_address_register(
-        control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,
-        bus: Rc<RefCell<Bus>>,
-    ) -> Rc<RefCell<RORegister>> {
-        Rc::new(RefCell::new(RORegister::new(
-            "Mem. Address Reg.".to_string(),
-            WORD_SIZE - OPCODE_SIZE,
-            bus,
-            BusSelector::LSB,
-            ControlLine::MI,
-            control_links,
-        )))
-    }
-
-    fn init_ram(
-        control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,
-        bus: Rc<RefCell<Bus>>,
-        mar: Rc<RefCell<RORegister>>,
-        ramdump: Vec<u8>,
-    ) -> RAM {
-        RAM::new(control_links, bus, mar, &convert_ramdump_to_bitvec(&ramdump))
-    }
-
-    fn init_instruction_register(
-        control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,
-        bus: Rc<RefCell<Bus>>,
-    ) -> Rc<RefCell<RWRegister>> {
-        Rc::new(RefCell::new(RWRegister::new(
-            "Instruction Reg.".to_string(),
-            OPCODE_SIZE,
-            bus,
-            BusSelector::HSB,
-            ControlLine::II,
-            ControlLine::IO,
-            control_links,
-        )))
-    }
-
-    fn init_output_register(
-        control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,
-        bus: Rc<RefCell<Bus>>,
-    ) -> RORegister {
-        RORegister::new(
-            "Output Register".to_string(),
-            WORD_SIZE,
-            bus,
-            BusSelector::LSB,
-            ControlLine::OI,
-            control_links,
-        )
     }
 
-    fn init_controller(
-        control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,
-        sequencer: Rc<RefCell<Sequencer>>,
-    ) -> Controller {
-        Controller::new(control_links, sequencer)
+    /// Number of bits held by a register.
+    pub fn register_width(&self, register: RegisterId) -> usize {
+        match register {
+            RegisterId::PC => ADDRESS_SIZE,
+            RegisterId::MAR => self.ram.mar.width(),
+            RegisterId::IR => self.ir.width(),
+            RegisterId::A => self.reg_a.width(),
+            RegisterId::B => self.reg_b.width(),
+            RegisterId::OUT => self.reg_out.width(),
+        }
+    }
+
+    /// Adder output for the current A and B registers and SU line.
+    pub fn alu_status(&self) -> ALUStatus {
+        let subtracting = self.control_links[&ControlLine::SU].get_state();
+        ALUStatus {
+            result: add(self.reg_a.read(), self.reg_b.read(), subtracting),
+            subtracting,
+            flags: self.alu.flags,
+        }
+    }
+
+    /// Forces a control line high, then low, then releases it, like a jumper wire to a rail that overrides the controller.
+    pub fn toggle_user_control(&mut self, line: &ControlLine) {
+        let next = match self.control_links[line].get_forced() {
+            None => Some(true),
+            Some(true) => Some(false),
+            Some(false) => None,
+        };
+        self.control_links.force(line, next);
+    }
+
+    pub fn release_user_controls(&mut self) {
+        for line in ControlLine::all() {
+            self.control_links.force(&line, None);
+        }
+    }
+
+    /// Switches between microcoded execution and manual control of the lines, the sequencer holds its step meanwhile.
+    pub fn toggle_manual_mode(&mut self) {
+        let entering = self.controller.is_enabled();
+        self.controller.set_enabled(&mut self.control_links, !entering);
+        if !entering {
+            // Unplug the jumper wires before handing back to the controller
+            self.release_user_controls();
+        }
+    }
+
+    pub fn is_manual(&self) -> bool {
+        !self.controller.is_enabled()
+    }
+
+    pub fn toggle_breakpoint(&mut self, address: usize) {
+        if !self.breakpoints.remove(&address) {
+            self.breakpoints.insert(address);
+        }
+    }
+
+    /// Whether the next instruction to fetch sits on a breakpoint.
+    pub fn at_breakpoint(&self) -> bool {
+        !self.is_manual() && self.controller.sequencer.get_step() == 0 && self.breakpoints.contains(&(self.pc.read() as usize))
+    }
+
+    /// Every value latched into the Output Register since the last reset, oldest first.
+    pub fn outputs(&self) -> impl DoubleEndedIterator<Item = &OutputRecord> + ExactSizeIterator {
+        self.output_log.iter()
+    }
+
+    /// Whether HLT stopped the machine, latched until reset.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Whether more than one module currently drives the bus.
+    pub fn bus_contention(&self) -> bool {
+        let levels = self.control_links.word();
+        BUS_OUTPUTS.iter().filter(|line| levels & line.bit() != 0).count() > 1
+    }
+
+    /// Runs the next clock edge, without any clock thread.
+    pub fn tick_half(&mut self) -> StepResult {
+        let mut result = StepResult::default();
+        if !self.halted {
+            let clock_state = !self.clock_state;
+            self.on_clock_high(clock_state);
+            if clock_state {
+                result.cycles = 1;
+                result.contention = self.bus_contention();
+            } else {
+                result.breakpoint = self.at_breakpoint();
+            }
+        }
+        result.halted = self.halted;
+        result
+    }
+
+    /// Runs a whole clock cycle, finishing the current one first if the clock is high.
+    pub fn tick(&mut self) -> StepResult {
+        let mut result = StepResult::default();
+        if self.clock_state {
+            result.merge(self.tick_half());
+        }
+        for _ in 0..2 {
+            if result.stopped() {
+                break;
+            }
+            result.merge(self.tick_half());
+        }
+        result
+    }
+
+    /// Runs the current T-state, each microinstruction takes one clock cycle.
+    pub fn step_microinstruction(&mut self) -> StepResult {
+        self.tick()
+    }
+
+    /// Runs until the next fetch, in manual mode the sequencer holds so this is a single cycle.
+    pub fn step_instruction(&mut self) -> StepResult {
+        let mut result = self.tick();
+        while !result.halted && !self.is_manual() && self.controller.sequencer.get_step() != 0 {
+            result.merge(self.tick());
+        }
+        result
+    }
+
+    /// Runs whole cycles until the predicate holds after one of them, the machine halts,
+    /// a breakpoint is reached or `max_cycles` have run.
+    pub fn run_until<F: FnMut(&Computer) -> bool>(&mut self, mut predicate: F, max_cycles: usize) -> StepResult {
+        let mut result = StepResult::default();
+        while result.cycles < max_cycles {
+            result.merge(self.tick());
+            if result.stopped() || predicate(self) {
+                break;
+            }
+        }
+        result
     }
 
     pub fn on_clock_high(&mut self, clock_state: bool) {
+        self.clock_state = clock_state;
         if clock_state {
-            self.controller.on_clock_high();
-            self.pc.on_clock_high();
-            self.mar.borrow_mut().on_clock_high();
-            self.ram.on_clock_high();
-            self.ir.borrow_mut().on_clock_high();
-            self.reg_a.borrow_mut().on_clock_high();
-            self.reg_b.borrow_mut().on_clock_high();
-            self.reg_out.on_clock_high();
+            self.controller.on_clock_high(&mut self.control_links);
+            // Only the modules whose lines are asserted on this edge are run
+            let levels = self.control_links.word();
+            let asserted = |line: ControlLine| levels & line.bit() != 0;
+            // Every module drives before any of them latches
+            if asserted(ControlLine::CO) {
+                self.pc.drive_bus(&mut self.bus);
+            }
+            if asserted(ControlLine::RO) {
+                self.ram.drive_bus(&mut self.bus);
+            }
+            if asserted(ControlLine::IO) {
+                self.ir.drive_bus(&mut self.bus);
+            }
+            if asserted(ControlLine::EO) {
+                let result = add(self.reg_a.read(), self.reg_b.read(), asserted(ControlLine::SU));
+                self.bus.write(result.value, WORD_SIZE);
+            }
+            if asserted(ControlLine::AO) {
+                self.reg_a.drive_bus(&mut self.bus);
+            }
+            if asserted(ControlLine::BO) {
+                self.reg_b.drive_bus(&mut self.bus);
+            }
+            if asserted(ControlLine::J) {
+                self.pc.jump(&self.bus);
+            }
+            if asserted(ControlLine::CE) {
+                self.pc.increment();
+            }
+            if asserted(ControlLine::MI) {
+                self.ram.mar.latch(&self.bus);
+            }
+            if asserted(ControlLine::RI) {
+                self.ram.latch(&self.bus);
+            }
+            if asserted(ControlLine::II) {
+                self.ir.latch(&self.bus);
+            }
+            if asserted(ControlLine::FI) {
+                // Flags follow A and B as they were before this edge latches them
+                let result = add(self.reg_a.read(), self.reg_b.read(), asserted(ControlLine::SU));
+                self.alu.latch_flags(&result);
+            }
+            if asserted(ControlLine::AI) {
+                self.reg_a.latch(&self.bus);
+            }
+            if asserted(ControlLine::BI) {
+                self.reg_b.latch(&self.bus);
+            }
+            if asserted(ControlLine::OI) {
+                self.reg_out.latch(&self.bus);
+                self.output_log.push(OutputRecord {
+                    cycle: self.cycles,
+                    value: self.reg_out.read(),
+                });
+            }
+            if asserted(ControlLine::HLT) {
+                self.halted = true;
+            }
+            self.cycles += 1;
         } else {
-            self.controller.on_clock_low();
-            self.sequencer.borrow_mut().increment_step(&self.ir);
+            self.controller.on_clock_low(&mut self.control_links);
+            if !self.is_manual() {
+                self.controller.sequencer.increment_step(self.ir.read(), self.alu.flags);
+            }
         }
     }
 }
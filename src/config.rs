@@ -1,5 +1,7 @@
 
 pub const CLOCK_FREQUENCY: usize = 1; /* Hz */
+pub const MAX_FREQUENCY: usize = 100_000; /* Hz */
+pub const FRAME_RATE: u32 = 30; /* Redraws per second */
 
 pub const WORD_SIZE: usize = 8; /* Bits */
 pub const OPCODE_SIZE: usize = 4; /* Bits */
@@ -1,45 +1,59 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
-
-use crate::{clock::ClockDriven, control::{control::ControlLine, sequencer::Sequencer}, display::renderer, link::Link};
+use crate::{control::{line::ControlLine, sequencer::Sequencer}, link::ControlLinks};
 
 // Controller
 pub struct Controller {
-    sequencer: Rc<RefCell<Sequencer>>,
-    // Links
-    pub control_links: HashMap<ControlLine, Rc<RefCell<Link>>>
+    pub sequencer: Sequencer,
+    enabled: bool,
+    // Endpoint the controller drives on every line
+    endpoint: usize,
 }
 
 impl Controller {
-    pub fn new(
-        control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,
-        sequencer: Rc<RefCell<Sequencer>>
-    ) -> Self {
-        for (_line, link) in &control_links {
-            link.borrow_mut().add_endpoint("CONTROLLER".to_string());
-        }
+    pub fn new(control_links: &mut ControlLinks) -> Self {
         Self {
-            sequencer,
-            control_links
+            sequencer: Sequencer::new(),
+            enabled: true,
+            endpoint: control_links.add_endpoint("CONTROLLER"),
         }
     }
 
-    pub fn drive_step_controls(&mut self, microcode_step: Vec<ControlLine>, state: bool) {
+    pub fn drive_step_controls(&self, control_links: &mut ControlLinks, microcode_step: &[ControlLine], state: bool) {
         for control in microcode_step {
-            self.control_links[&control].borrow_mut().set_endpoint("CONTROLLER".to_string(), state);
+            control_links.set_endpoint(control, self.endpoint, state);
         }
     }
 
-    pub fn on_clock_low(&mut self) {
-        // drive all current signals low
-        let step_controls = self.sequencer.borrow().get_current_step_controls();
-        self.drive_step_controls(step_controls, false);
+    pub fn release_all(&self, control_links: &mut ControlLinks) {
+        for line in ControlLine::all() {
+            control_links.set_endpoint(&line, self.endpoint, false);
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Disconnects the controller from the control lines, leaving them to manual control.
+    pub fn set_enabled(&mut self, control_links: &mut ControlLinks, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.release_all(control_links);
+        }
     }
-}
 
-impl ClockDriven for Controller {
-    fn on_clock_high(&mut self) {
+    pub fn on_clock_high(&self, control_links: &mut ControlLinks) {
+        if !self.enabled {
+            return;
+        }
         // Run current step
-        let step_controls = self.sequencer.borrow().get_current_step_controls();
-        self.drive_step_controls(step_controls, true);
+        self.drive_step_controls(control_links, self.sequencer.get_current_step_controls(), true);
+    }
+
+    pub fn on_clock_low(&self, control_links: &mut ControlLinks) {
+        if !self.enabled {
+            return;
+        }
+        // drive all current signals low
+        self.drive_step_controls(control_links, self.sequencer.get_current_step_controls(), false);
     }
-}
\ No newline at end of file
+}
@@ -1,10 +1,8 @@
-use std::collections::HashMap;
+use std::{fmt, str::FromStr};
 
-use bit_vec::BitVec;
+use crate::{alu::Flags, bitvecutils::{get_bits, parse_word, Word}, config::OPCODE_SIZE, control::line::ControlLine, pc::ADDRESS_SIZE};
 
-use crate::{bitvecutils::BinaryDisplay, config::OPCODE_SIZE, control::control::ControlLine};
-
-#[derive(PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum OpCode {
     NOP, //No Operation
     LDA, //Load A
@@ -12,79 +10,262 @@ pub enum OpCode {
     SUB, //Subtract
     MUL, //Multiply
     OUT, //Output
+    STA, //Store A
+    JMP, //Jump
+    JC,  //Jump if carry
+    JZ,  //Jump if zero
     HLT  //Halt
 }
 
+impl fmt::Display for OpCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            OpCode::NOP => "NOP",
+            OpCode::LDA => "LDA",
+            OpCode::ADD => "ADD",
+            OpCode::SUB => "SUB",
+            OpCode::MUL => "MUL",
+            OpCode::OUT => "OUT",
+            OpCode::STA => "STA",
+            OpCode::JMP => "JMP",
+            OpCode::JC => "JC",
+            OpCode::JZ => "JZ",
+            OpCode::HLT => "HLT",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for OpCode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "NOP" => Ok(OpCode::NOP),
+            "LDA" => Ok(OpCode::LDA),
+            "ADD" => Ok(OpCode::ADD),
+            "SUB" => Ok(OpCode::SUB),
+            "MUL" => Ok(OpCode::MUL),
+            "OUT" => Ok(OpCode::OUT),
+            "STA" => Ok(OpCode::STA),
+            "JMP" => Ok(OpCode::JMP),
+            "JC" => Ok(OpCode::JC),
+            "JZ" => Ok(OpCode::JZ),
+            "HLT" => Ok(OpCode::HLT),
+            _ => Err(()),
+        }
+    }
+}
+
+impl OpCode {
+    pub fn all() -> [OpCode; 11] {
+        [
+            OpCode::NOP, OpCode::LDA, OpCode::ADD, OpCode::SUB, OpCode::MUL, OpCode::OUT,
+            OpCode::STA, OpCode::JMP, OpCode::JC, OpCode::JZ, OpCode::HLT,
+        ]
+    }
+
+    /// Value of the high bits of the instruction word.
+    pub fn code(&self) -> Word {
+        match self {
+            OpCode::NOP => 0b0000,
+            OpCode::LDA => 0b0001,
+            OpCode::ADD => 0b0010,
+            OpCode::SUB => 0b0011,
+            OpCode::MUL => 0b0100,
+            OpCode::OUT => 0b0101,
+            OpCode::STA => 0b0110,
+            OpCode::JMP => 0b0111,
+            OpCode::JC => 0b1000,
+            OpCode::JZ => 0b1001,
+            OpCode::HLT => 0b1111,
+        }
+    }
+
+    /// Whether the low bits of the instruction word are a memory address.
+    pub fn has_operand(&self) -> bool {
+        matches!(self, OpCode::LDA | OpCode::ADD | OpCode::SUB | OpCode::MUL | OpCode::STA | OpCode::JMP | OpCode::JC | OpCode::JZ)
+    }
+}
+
+/// Control lines asserted on each step of an instruction, the tables are static so decoding never allocates.
+pub type Microcode = &'static [&'static [ControlLine]];
+
+const JUMP: Microcode = &[&[ControlLine::J]];
+
+// Opcodes indexed by their code, decoding is a single lookup
+#[derive(Clone)]
 pub struct Decoder {
-    opcodes: HashMap<OpCode, BitVec>
+    opcodes: [Option<OpCode>; 1 << OPCODE_SIZE]
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Decoder {
     pub fn new() -> Self {
-        let mut map = HashMap::new();
-        map.insert(OpCode::NOP, BitVec::from_bytes(&[0b00000000]));
-        map.insert(OpCode::LDA, BitVec::from_bytes(&[0b10000000]));
-        map.insert(OpCode::ADD, BitVec::from_bytes(&[0b01000000]));
-        map.insert(OpCode::SUB, BitVec::from_bytes(&[0b11000000]));
-        map.insert(OpCode::MUL, BitVec::from_bytes(&[0b00100000]));
-        map.insert(OpCode::OUT, BitVec::from_bytes(&[0b10100000]));
-        map.insert(OpCode::HLT, BitVec::from_bytes(&[0b11110000]));
-        for bitvec in map.values_mut() {
-            bitvec.truncate(OPCODE_SIZE);
+        let mut opcodes = [None; 1 << OPCODE_SIZE];
+        for opcode in OpCode::all() {
+            opcodes[opcode.code() as usize] = Some(opcode);
         }
         Self {
-            opcodes: map
+            opcodes
+        }
+    }
+
+    pub fn decode_opcode(&self, instruction: Word) -> Option<OpCode> {
+        self.opcodes.get(instruction as usize).copied().flatten()
+    }
+
+    /// Builds an instruction word from "MNEMONIC [operand]" text.
+    pub fn assemble(&self, text: &str) -> Option<Word> {
+        let mut tokens = text.split_whitespace();
+        let opcode = tokens.next()?.parse::<OpCode>().ok()?;
+        let operand = match tokens.next() {
+            Some(operand) => parse_word(operand, ADDRESS_SIZE)?,
+            None => 0,
+        };
+        if tokens.next().is_some() {
+            return None;
+        }
+        Some(opcode.code() << ADDRESS_SIZE | operand)
+    }
+
+    pub fn disassemble(&self, word: Word) -> Option<String> {
+        let opcode = self.decode_opcode(get_bits(word, ADDRESS_SIZE, OPCODE_SIZE))?;
+        if opcode.has_operand() {
+            Some(format!("{} {}", opcode, get_bits(word, 0, ADDRESS_SIZE)))
+        } else {
+            Some(opcode.to_string())
         }
     }
 
-    pub fn decode_instruction(&self, instruction: BitVec) -> Vec<Vec<ControlLine>> {
-        // Loop over each opcode and check if it matches the instruction
-        for (opcode, opcode_bits) in &self.opcodes {
-            if instruction == *opcode_bits {
-                match opcode {
-                    OpCode::NOP => {
-                        // NOP: No Operation
-                        return vec![];
-                    }
-                    OpCode::LDA => {
-                        // LDA: Load A
-                        return vec![
-                            vec![ControlLine::MI],
-                            vec![ControlLine::RO, ControlLine::AI],
-                        ];
-                    }
-                    OpCode::ADD => {
-                        // ADD: Add
-                        return vec![
-                            vec![ControlLine::MI],
-                            vec![ControlLine::RO, ControlLine::BI],
-                            vec![ControlLine::EO, ControlLine::AI],
-                        ];
-                    }
-                    OpCode::SUB => {
-                        // SUB: Subtract
-                        return vec![
-                            vec![ControlLine::MI],
-                            vec![ControlLine::RO, ControlLine::BI],
-                            vec![ControlLine::SU, ControlLine::AI],
-                        ];
-                    }
-                    OpCode::MUL => {
-                        // MUL: Multiply
-                        return vec![];
-                    }
-                    OpCode::OUT => {
-                        // OUT: Output
-                        return vec![vec![ControlLine::AO, ControlLine::OI]];
-                    }
-                    OpCode::HLT => {
-                        // HLT: Halt
-                        return vec![vec![ControlLine::HLT]];
-                    }
-                }
+    /// Steps run after the fetch, the address operand is still on the bus from the fetch.
+    pub fn get_microcode(&self, opcode: OpCode, flags: Flags) -> Microcode {
+        match opcode {
+            OpCode::NOP => {
+                // NOP: No Operation
+                &[]
+            }
+            OpCode::LDA => {
+                // LDA: Load A
+                &[
+                    &[ControlLine::MI],
+                    &[ControlLine::RO, ControlLine::AI],
+                ]
+            }
+            OpCode::ADD => {
+                // ADD: Add
+                &[
+                    &[ControlLine::MI],
+                    &[ControlLine::RO, ControlLine::BI],
+                    &[ControlLine::EO, ControlLine::AI, ControlLine::FI],
+                ]
+            }
+            OpCode::SUB => {
+                // SUB: Subtract
+                &[
+                    &[ControlLine::MI],
+                    &[ControlLine::RO, ControlLine::BI],
+                    &[ControlLine::EO, ControlLine::SU, ControlLine::AI, ControlLine::FI],
+                ]
+            }
+            OpCode::MUL => {
+                // MUL: Multiply
+                &[]
+            }
+            OpCode::OUT => {
+                // OUT: Output
+                &[&[ControlLine::AO, ControlLine::OI]]
             }
+            OpCode::STA => {
+                // STA: Store A
+                &[
+                    &[ControlLine::MI],
+                    &[ControlLine::AO, ControlLine::RI],
+                ]
+            }
+            OpCode::JMP => {
+                // JMP: Jump
+                JUMP
+            }
+            OpCode::JC => {
+                // JC: Jump if the last addition carried
+                if flags.carry { JUMP } else { &[] }
+            }
+            OpCode::JZ => {
+                // JZ: Jump if the last result was zero
+                if flags.zero { JUMP } else { &[] }
+            }
+            OpCode::HLT => {
+                // HLT: Halt
+                &[&[ControlLine::HLT]]
+            }
+        }
+    }
+
+    /// Undefined opcodes run no steps after the fetch, `image::validate` reports them before loading.
+    pub fn decode_instruction(&self, instruction: Word, flags: Flags) -> Microcode {
+        match self.decode_opcode(instruction) {
+            Some(opcode) => self.get_microcode(opcode, flags),
+            None => &[],
         }
-        println!("{}: No matching Opcode", instruction.to_bin_string());
-        return vec![]
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_every_opcode_from_its_code() {
+        let decoder = Decoder::new();
+        for opcode in OpCode::all() {
+            assert!(decoder.decode_opcode(opcode.code()) == Some(opcode), "{}", opcode);
+            assert!(opcode.to_string().parse::<OpCode>() == Ok(opcode));
+        }
+        assert!(decoder.decode_opcode(0b1110).is_none());
+    }
+
+    #[test]
+    fn assembles_and_disassembles() {
+        let decoder = Decoder::new();
+        assert_eq!(decoder.assemble("LDA 14"), Some(0x1E));
+        assert_eq!(decoder.assemble("jmp 0x3"), Some(0x73));
+        assert_eq!(decoder.assemble("OUT"), Some(0x50));
+        assert_eq!(decoder.disassemble(0x2F).as_deref(), Some("ADD 15"));
+        assert_eq!(decoder.disassemble(0xF0).as_deref(), Some("HLT"));
+        assert_eq!(decoder.disassemble(0xE0), None);
+    }
+
+    #[test]
+    fn rejects_malformed_assembly() {
+        let decoder = Decoder::new();
+        assert_eq!(decoder.assemble("LDA 16"), None);
+        assert_eq!(decoder.assemble("FOO 1"), None);
+        assert_eq!(decoder.assemble("ADD 1 2"), None);
+        assert_eq!(decoder.assemble(""), None);
+    }
+
+    #[test]
+    fn conditional_jumps_follow_the_flags() {
+        let decoder = Decoder::new();
+        let jump: Microcode = &[&[ControlLine::J]];
+        let carry = Flags { carry: true, zero: false };
+        let zero = Flags { carry: false, zero: true };
+        assert!(decoder.get_microcode(OpCode::JC, carry) == jump);
+        assert!(decoder.get_microcode(OpCode::JC, zero).is_empty());
+        assert!(decoder.get_microcode(OpCode::JZ, zero) == jump);
+        assert!(decoder.get_microcode(OpCode::JZ, carry).is_empty());
+        assert!(decoder.get_microcode(OpCode::JMP, Flags::default()) == jump);
+    }
+
+    #[test]
+    fn undefined_opcodes_have_no_microcode() {
+        let decoder = Decoder::new();
+        assert!(decoder.decode_instruction(0b1110, Flags::default()).is_empty());
+        assert!(decoder.decode_instruction(0b1111, Flags::default()) == [[ControlLine::HLT].as_slice()]);
+    }
+}
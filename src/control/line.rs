@@ -41,6 +41,28 @@ pub enum ControlLine {
     FI,
 }
 
+impl ControlLine {
+    /// Every control line, in the order they are laid out on the control word.
+    pub fn all() -> [ControlLine; 18] {
+        [
+            ControlLine::CLK, ControlLine::HLT, ControlLine::MI, ControlLine::RI, ControlLine::RO,
+            ControlLine::II, ControlLine::IO, ControlLine::AI, ControlLine::AO, ControlLine::EO,
+            ControlLine::SU, ControlLine::BI, ControlLine::BO, ControlLine::OI, ControlLine::CE,
+            ControlLine::CO, ControlLine::J, ControlLine::FI,
+        ]
+    }
+
+    /// Position of the line in `ControlLine::all`.
+    pub fn index(&self) -> usize {
+        self.clone() as usize
+    }
+
+    /// Bit of the line in a control word, see `link::ControlLinks::word`.
+    pub fn bit(&self) -> u32 {
+        1 << self.index()
+    }
+}
+
 impl fmt::Display for ControlLine {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
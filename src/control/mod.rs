@@ -1,4 +1,4 @@
-pub mod control;
+pub mod line;
 pub mod controller;
 pub mod decoder;
 pub mod sequencer;
\ No newline at end of file
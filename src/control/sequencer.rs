@@ -1,72 +1,91 @@
-use std::{cell::RefCell, rc::Rc};
-
-use crate::{control::control::ControlLine, control::decoder::Decoder, memory::register::RWRegister};
+use crate::{alu::Flags, bitvecutils::Word, control::line::ControlLine, control::decoder::{Decoder, Microcode, OpCode}};
 
+// Steps shared by every instruction, loading it into the IR and incrementing the PC
+const FETCH_MICROCODE: Microcode = &[
+    &[ControlLine::CO, ControlLine::MI],
+    &[ControlLine::RO, ControlLine::II],
+    &[ControlLine::CE],
+];
 
 // Sequencer
+#[derive(Clone)]
 pub struct Sequencer {
-    fetch_microcode: Vec<Vec<ControlLine>>,
-    instruction_microcode: Vec<Vec<ControlLine>>,
+    fetch_microcode: Microcode,
+    instruction_microcode: Microcode,
+    opcode: Option<OpCode>,
     microcode_step: u8,
     decoder: Decoder
 }
 
+impl Default for Sequencer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Sequencer {
     pub fn new() -> Self {
         Self {
-            fetch_microcode: vec![
-                vec![ControlLine::CO, ControlLine::MI],
-                vec![ControlLine::RO, ControlLine::II],
-                vec![ControlLine::CE],
-            ],
-            instruction_microcode: Vec::new(),
+            fetch_microcode: FETCH_MICROCODE,
+            instruction_microcode: &[],
+            opcode: None,
             microcode_step: 0,
             decoder: Decoder::new()
         }
     }
 
-    pub fn get_current_step_controls(&self) -> Vec<ControlLine> {
+    pub fn reset(&mut self) {
+        self.instruction_microcode = &[];
+        self.opcode = None;
+        self.microcode_step = 0;
+    }
+
+    pub fn get_decoder(&self) -> &Decoder {
+        &self.decoder
+    }
+
+    pub fn get_step(&self) -> usize {
+        self.microcode_step as usize
+    }
+
+    pub fn get_fetch_microcode(&self) -> Microcode {
+        self.fetch_microcode
+    }
+
+    pub fn get_instruction_microcode(&self) -> Microcode {
+        self.instruction_microcode
+    }
+
+    /// Instruction being executed, None while the next one is being fetched.
+    pub fn get_opcode(&self) -> Option<OpCode> {
+        self.opcode
+    }
+
+    pub fn get_current_step_controls(&self) -> &'static [ControlLine] {
         if self.microcode_step < self.fetch_microcode.len() as u8 {
-            return self.fetch_microcode[self.microcode_step as usize].clone();
+            return self.fetch_microcode[self.microcode_step as usize];
         } else {
             let rstep = self.microcode_step as usize - self.fetch_microcode.len();
             if rstep < self.instruction_microcode.len() {
-                return self.instruction_microcode[rstep].clone();
-            }
-        }
-        return vec![]
-    }
-
-    pub fn get_prev_step_controls(&self) -> Vec<ControlLine> {
-        if self.microcode_step > 0 {
-            let prev_step = self.microcode_step - 1;
-            if prev_step < self.fetch_microcode.len() as u8 {
-                return self.fetch_microcode[prev_step as usize].clone();
-            } else {
-                let rstep = prev_step as usize - self.fetch_microcode.len();
-                if rstep < self.instruction_microcode.len() {
-                    return self.instruction_microcode[rstep].clone();
-                }
-            }
-        } else {
-            if self.instruction_microcode.len() > 0 {
-                return self.instruction_microcode[self.instruction_microcode.len()-1].clone();
-            } else {
-                return self.fetch_microcode[self.fetch_microcode.len()-1].clone();
+                return self.instruction_microcode[rstep];
             }
         }
-        return vec![]
+        &[]
     }
 
-    pub fn increment_step(&mut self, ir: &Rc<RefCell<RWRegister>>) {
+    /// Moves to the next step, decoding `instruction` once the fetch is over.
+    pub fn increment_step(&mut self, instruction: Word, flags: Flags) {
         self.microcode_step += 1;
         // Handle instruction decoding when fetch is over
         if self.microcode_step == self.fetch_microcode.len() as u8 {
-            self.instruction_microcode = self.decoder.decode_instruction(ir.borrow().read());
+            self.opcode = self.decoder.decode_opcode(instruction);
+            // Conditional jumps pick their microcode from the latched flags
+            self.instruction_microcode = self.decoder.decode_instruction(instruction, flags);
         }
         // Handle cycle end
         if self.microcode_step as usize >= (self.fetch_microcode.len() + self.instruction_microcode.len()) {
             self.microcode_step = 0;
+            self.opcode = None;
         }
     }
 }
@@ -0,0 +1,96 @@
+use crate::{bitvecutils::parse_word, computer::{Computer, RegisterId}, config::WORD_SIZE, control::decoder::Decoder, memory::ram::RAM};
+
+// Front panel programming state of the RAM Inspector
+pub struct RamInspector {
+    pub program_mode: bool,
+    pub cursor: usize,
+    pub input: Option<String>,
+    pub error: bool,
+}
+
+impl Default for RamInspector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RamInspector {
+    pub fn new() -> Self {
+        Self {
+            program_mode: false,
+            cursor: 0,
+            input: None,
+            error: false,
+        }
+    }
+
+    pub fn toggle_program_mode(&mut self) {
+        self.program_mode = !self.program_mode;
+        self.input = None;
+        self.error = false;
+    }
+
+    pub fn move_cursor(&mut self, delta: isize, ram: &RAM) {
+        let size = ram.memory.len() as isize;
+        if size > 0 {
+            self.cursor = (self.cursor as isize + delta).rem_euclid(size) as usize;
+        }
+    }
+
+    pub fn start_edit(&mut self) {
+        self.input = Some(String::new());
+        self.error = false;
+    }
+
+    pub fn cancel_edit(&mut self) {
+        self.input = None;
+        self.error = false;
+    }
+
+    /// Writes the edited value (number or mnemonic) at the cursor, keeping the input on parse errors.
+    pub fn commit_edit(&mut self, ram: &mut RAM, decoder: &Decoder) {
+        if let Some(input) = &self.input {
+            let value = parse_word(input, WORD_SIZE).or_else(|| decoder.assemble(input));
+            match value {
+                Some(value) => {
+                    ram.write_word(self.cursor, value);
+                    self.input = None;
+                    self.error = false;
+                    self.move_cursor(1, ram);
+                }
+                None => self.error = true,
+            }
+        }
+    }
+}
+
+// Hand edit of a register value
+pub struct RegisterEdit {
+    pub register: RegisterId,
+    pub input: String,
+    pub error: bool,
+}
+
+impl RegisterEdit {
+    pub fn new(register: RegisterId) -> Self {
+        Self {
+            register,
+            input: String::new(),
+            error: false,
+        }
+    }
+
+    /// Writes the value into the register, returns false and flags the input on parse errors.
+    pub fn commit(&mut self, computer: &mut Computer) -> bool {
+        match parse_word(&self.input, computer.register_width(self.register)) {
+            Some(value) => {
+                computer.write_register(self.register, value);
+                true
+            }
+            None => {
+                self.error = true;
+                false
+            }
+        }
+    }
+}
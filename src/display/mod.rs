@@ -1,2 +1,6 @@
+pub mod inspector;
+pub mod output;
 pub mod renderer;
-pub mod widgets;
\ No newline at end of file
+pub mod theme;
+pub mod widgets;
+pub mod waveform;
\ No newline at end of file
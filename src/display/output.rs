@@ -0,0 +1,71 @@
+use crate::bitvecutils::{mask, Word};
+
+pub const OUTPUT_DIGITS: usize = 4;
+
+// Interpretation of the Output Register, as selected on the output module
+#[derive(Copy, Clone, PartialEq)]
+pub enum OutputMode {
+    Unsigned,
+    Signed,
+    Hex,
+}
+
+impl OutputMode {
+    pub fn next(&self) -> Self {
+        match self {
+            OutputMode::Unsigned => OutputMode::Signed,
+            OutputMode::Signed => OutputMode::Hex,
+            OutputMode::Hex => OutputMode::Unsigned,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OutputMode::Unsigned => "UNSIGNED",
+            OutputMode::Signed => "SIGNED",
+            OutputMode::Hex => "HEX",
+        }
+    }
+}
+
+/// Text shown on the digits, right aligned and blank padded like the hardware.
+pub fn format_output(value: Word, width: usize, mode: OutputMode) -> String {
+    let unsigned = (value & mask(width)) as usize;
+    let text = match mode {
+        OutputMode::Unsigned => unsigned.to_string(),
+        OutputMode::Signed => {
+            // Two's complement on the register width
+            if width > 0 && unsigned >> (width - 1) & 1 == 1 {
+                format!("-{}", (1usize << width) - unsigned)
+            } else {
+                unsigned.to_string()
+            }
+        }
+        OutputMode::Hex => format!("{:X}", unsigned),
+    };
+    format!("{:>1$}", text, OUTPUT_DIGITS)
+}
+
+/// Three rows of the seven segment glyph for a displayable character.
+pub fn segment_glyph(c: char) -> [&'static str; 3] {
+    match c {
+        '0' => ["┏━┓", "┃ ┃", "┗━┛"],
+        '1' => ["  ┓", "  ┃", "  ┻"],
+        '2' => ["╺━┓", "┏━┛", "┗━╸"],
+        '3' => ["╺━┓", " ━┫", "╺━┛"],
+        '4' => ["╻ ╻", "┗━┫", "  ╹"],
+        '5' => ["┏━╸", "┗━┓", "╺━┛"],
+        '6' => ["┏━╸", "┣━┓", "┗━┛"],
+        '7' => ["╺━┓", "  ┃", "  ╹"],
+        '8' => ["┏━┓", "┣━┫", "┗━┛"],
+        '9' => ["┏━┓", "┗━┫", "╺━┛"],
+        'A' => ["┏━┓", "┣━┫", "╹ ╹"],
+        'B' => ["╻  ", "┣━┓", "┗━┛"],
+        'C' => ["┏━╸", "┃  ", "┗━╸"],
+        'D' => ["  ╻", "┏━┫", "┗━┛"],
+        'E' => ["┏━╸", "┣━╸", "┗━╸"],
+        'F' => ["┏━╸", "┣━╸", "╹  "],
+        '-' => ["   ", "╺━╸", "   "],
+        _ => ["   ", "   ", "   "],
+    }
+}
@@ -1,13 +1,13 @@
 use std::{cell::RefCell, io::Stdout, rc::Rc};
 
-use crossterm::{execute, terminal::{disable_raw_mode, LeaveAlternateScreen}};
-use ratatui::{layout::{Constraint, Direction, Layout, Rect}, prelude::CrosstermBackend, Frame, Terminal};
+use crossterm::{event::{DisableMouseCapture, EnableMouseCapture}, execute};
+use ratatui::{layout::{Constraint, Direction, Layout, Position, Rect}, prelude::CrosstermBackend, Frame, Terminal};
 
-use crate::{clock::Clock, computer::Computer, control::control::ControlLine};
+use crate::{clock::Clock, computer::{Computer, RegisterId}, control::line::ControlLine};
 
-use super::widgets::{render_all_links, render_bus_connection, render_h_link, render_ic_connection, render_ram_inspector, BusConnection, ICConnection};
+use super::{inspector::{RamInspector, RegisterEdit}, output::OutputMode, theme::Theme, waveform::Waveform, widgets::{render_all_links, render_bus_connection, render_example_menu, render_h_link, render_ic_connection, render_help, render_notice, render_output_display, render_output_log, render_ram_inspector, render_register_edit, render_too_small, BusConnection, ICConnection, Themed}};
 
-fn render_left(frame: &mut Frame, left_inner_layout: &Rc<[Rect]>, clock: &Rc<RefCell<Clock>>, computer: &Computer) {
+fn render_left(frame: &mut Frame, left_inner_layout: &Rc<[Rect]>, clock: &Rc<RefCell<Clock>>, computer: &Computer, theme: &Theme, regions: &mut Vec<(Rect, Target)>) {
     let clock_layout = Layout::default()
     .direction(Direction::Horizontal)
     .constraints(vec![
@@ -16,7 +16,7 @@ fn render_left(frame: &mut Frame, left_inner_layout: &Rc<[Rect]>, clock: &Rc<Ref
         Constraint::Percentage(20),
     ])
     .split(left_inner_layout[0]);
-    frame.render_widget( &*clock.borrow(), clock_layout[1]);
+    frame.render_widget(Themed::new(&*clock.borrow(), theme), clock_layout[1]);
     let mar_layout = Layout::default()
     .direction(Direction::Horizontal)
     .constraints(vec![
@@ -25,9 +25,11 @@ fn render_left(frame: &mut Frame, left_inner_layout: &Rc<[Rect]>, clock: &Rc<Ref
         Constraint::Percentage(20),
     ])
     .split(left_inner_layout[2]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::MI].borrow(), true, mar_layout[0]);
-    frame.render_widget(&*computer.mar.borrow(), mar_layout[1]);
-    render_bus_connection(frame, BusConnection::Left, computer.control_links[&ControlLine::MI].borrow().get_state(),mar_layout[2]);
+    render_h_link(frame, &computer.control_links[&ControlLine::MI], true, theme, mar_layout[0]);
+    regions.push((mar_layout[0], Target::Control(ControlLine::MI)));
+    frame.render_widget(Themed::new(&computer.ram.mar, theme), mar_layout[1]);
+    regions.push((mar_layout[1], Target::Register(RegisterId::MAR)));
+    render_bus_connection(frame, BusConnection::Left, computer.control_links[&ControlLine::MI].get_state(), theme, mar_layout[2]);
     let ram_layout = Layout::default()
     .direction(Direction::Horizontal)
     .constraints(vec![
@@ -43,10 +45,12 @@ fn render_left(frame: &mut Frame, left_inner_layout: &Rc<[Rect]>, clock: &Rc<Ref
         Constraint::Percentage(50),
     ])
     .split(ram_layout[0]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::RI].borrow(), true, ram_links_layout[0]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::RO].borrow(), true, ram_links_layout[1]);
+    render_h_link(frame, &computer.control_links[&ControlLine::RI], true, theme, ram_links_layout[0]);
+    regions.push((ram_links_layout[0], Target::Control(ControlLine::RI)));
+    render_h_link(frame, &computer.control_links[&ControlLine::RO], true, theme, ram_links_layout[1]);
+    regions.push((ram_links_layout[1], Target::Control(ControlLine::RO)));
     frame.render_widget(&computer.ram, ram_layout[1]);
-    render_bus_connection(frame, BusConnection::Both, computer.control_links[&ControlLine::RO].borrow().get_state(), ram_layout[2]);
+    render_bus_connection(frame, BusConnection::Both, computer.control_links[&ControlLine::RO].get_state(), theme, ram_layout[2]);
     let ir_layout = Layout::default()
     .direction(Direction::Horizontal)
     .constraints(vec![
@@ -62,13 +66,16 @@ fn render_left(frame: &mut Frame, left_inner_layout: &Rc<[Rect]>, clock: &Rc<Ref
         Constraint::Percentage(50),
     ])
     .split(ir_layout[0]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::II].borrow(), true, ir_links_layout[0]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::IO].borrow(), true, ir_links_layout[1]);
-    frame.render_widget(&*computer.ir.borrow(), ir_layout[1]);
-    render_bus_connection(frame, BusConnection::Both, computer.control_links[&ControlLine::II].borrow().get_state(), ir_layout[2]);
+    render_h_link(frame, &computer.control_links[&ControlLine::II], true, theme, ir_links_layout[0]);
+    regions.push((ir_links_layout[0], Target::Control(ControlLine::II)));
+    render_h_link(frame, &computer.control_links[&ControlLine::IO], true, theme, ir_links_layout[1]);
+    regions.push((ir_links_layout[1], Target::Control(ControlLine::IO)));
+    frame.render_widget(Themed::new(&computer.ir, theme), ir_layout[1]);
+    regions.push((ir_layout[1], Target::Register(RegisterId::IR)));
+    render_bus_connection(frame, BusConnection::Both, computer.control_links[&ControlLine::II].get_state(), theme, ir_layout[2]);
 }
 
-fn render_right(frame: &mut Frame, right_inner_layout: &Rc<[Rect]>, computer: &Computer) {
+fn render_right(frame: &mut Frame, right_inner_layout: &Rc<[Rect]>, computer: &Computer, output_mode: OutputMode, theme: &Theme, regions: &mut Vec<(Rect, Target)>) {
     let pc_layout = Layout::default()
     .direction(Direction::Horizontal)
     .constraints(vec![
@@ -85,11 +92,15 @@ fn render_right(frame: &mut Frame, right_inner_layout: &Rc<[Rect]>, computer: &C
         Constraint::Percentage(33),
     ])
     .split(pc_layout[2]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::CO].borrow(), false, pc_links_layout[0]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::J].borrow(), false, pc_links_layout[1]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::CE].borrow(), false, pc_links_layout[2]);
-    render_bus_connection(frame, BusConnection::Both, computer.control_links[&ControlLine::CO].borrow().get_state(),pc_layout[0]);
-    frame.render_widget(&computer.pc, pc_layout[1]);
+    render_h_link(frame, &computer.control_links[&ControlLine::CO], false, theme, pc_links_layout[0]);
+    regions.push((pc_links_layout[0], Target::Control(ControlLine::CO)));
+    render_h_link(frame, &computer.control_links[&ControlLine::J], false, theme, pc_links_layout[1]);
+    regions.push((pc_links_layout[1], Target::Control(ControlLine::J)));
+    render_h_link(frame, &computer.control_links[&ControlLine::CE], false, theme, pc_links_layout[2]);
+    regions.push((pc_links_layout[2], Target::Control(ControlLine::CE)));
+    render_bus_connection(frame, BusConnection::Both, computer.control_links[&ControlLine::CO].get_state(), theme, pc_layout[0]);
+    frame.render_widget(Themed::new(&computer.pc, theme), pc_layout[1]);
+    regions.push((pc_layout[1], Target::Register(RegisterId::PC)));
     let reg_a_layout = Layout::default()
     .direction(Direction::Horizontal)
     .constraints(vec![
@@ -105,11 +116,14 @@ fn render_right(frame: &mut Frame, right_inner_layout: &Rc<[Rect]>, computer: &C
         Constraint::Percentage(50),
     ])
     .split(reg_a_layout[2]);
-    frame.render_widget(&*computer.reg_a.borrow(), reg_a_layout[1]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::AI].borrow(), false, reg_a_links_layout[0]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::AO].borrow(), false, reg_a_links_layout[1]);
-    render_bus_connection(frame, BusConnection::Both, computer.control_links[&ControlLine::AI].borrow().get_state(),reg_a_layout[0]);
-    render_ic_connection(frame, ICConnection::Down, false, right_inner_layout[3]);
+    frame.render_widget(Themed::new(&computer.reg_a, theme), reg_a_layout[1]);
+    regions.push((reg_a_layout[1], Target::Register(RegisterId::A)));
+    render_h_link(frame, &computer.control_links[&ControlLine::AI], false, theme, reg_a_links_layout[0]);
+    regions.push((reg_a_links_layout[0], Target::Control(ControlLine::AI)));
+    render_h_link(frame, &computer.control_links[&ControlLine::AO], false, theme, reg_a_links_layout[1]);
+    regions.push((reg_a_links_layout[1], Target::Control(ControlLine::AO)));
+    render_bus_connection(frame, BusConnection::Both, computer.control_links[&ControlLine::AI].get_state(), theme, reg_a_layout[0]);
+    render_ic_connection(frame, ICConnection::Down, false, theme, right_inner_layout[3]);
     let alu_layout = Layout::default()
     .direction(Direction::Horizontal)
     .constraints(vec![
@@ -125,10 +139,12 @@ fn render_right(frame: &mut Frame, right_inner_layout: &Rc<[Rect]>, computer: &C
         Constraint::Percentage(50),
     ])
     .split(alu_layout[2]);
-    frame.render_widget(&*computer.alu.borrow(), alu_layout[1]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::EO].borrow(), false, alu_links_layout[0]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::SU].borrow(), false, alu_links_layout[1]);
-    render_bus_connection(frame, BusConnection::Both, computer.control_links[&ControlLine::EO].borrow().get_state(),alu_layout[0]);
+    frame.render_widget(Themed::new(&computer.alu_status(), theme), alu_layout[1]);
+    render_h_link(frame, &computer.control_links[&ControlLine::EO], false, theme, alu_links_layout[0]);
+    regions.push((alu_links_layout[0], Target::Control(ControlLine::EO)));
+    render_h_link(frame, &computer.control_links[&ControlLine::SU], false, theme, alu_links_layout[1]);
+    regions.push((alu_links_layout[1], Target::Control(ControlLine::SU)));
+    render_bus_connection(frame, BusConnection::Both, computer.control_links[&ControlLine::EO].get_state(), theme, alu_layout[0]);
     let reg_b_layout = Layout::default()
     .direction(Direction::Horizontal)
     .constraints(vec![
@@ -144,11 +160,14 @@ fn render_right(frame: &mut Frame, right_inner_layout: &Rc<[Rect]>, computer: &C
         Constraint::Percentage(50),
     ])
     .split(reg_b_layout[2]);
-    render_ic_connection(frame, ICConnection::Up, false, right_inner_layout[5]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::BI].borrow(), false, reg_b_links_layout[0]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::BO].borrow(), false, reg_b_links_layout[1]);
-    frame.render_widget(&*computer.reg_b.borrow(), reg_b_layout[1]);
-    render_bus_connection(frame, BusConnection::Both, computer.control_links[&ControlLine::BI].borrow().get_state(),reg_b_layout[0]);
+    render_ic_connection(frame, ICConnection::Up, false, theme, right_inner_layout[5]);
+    render_h_link(frame, &computer.control_links[&ControlLine::BI], false, theme, reg_b_links_layout[0]);
+    regions.push((reg_b_links_layout[0], Target::Control(ControlLine::BI)));
+    render_h_link(frame, &computer.control_links[&ControlLine::BO], false, theme, reg_b_links_layout[1]);
+    regions.push((reg_b_links_layout[1], Target::Control(ControlLine::BO)));
+    frame.render_widget(Themed::new(&computer.reg_b, theme), reg_b_layout[1]);
+    regions.push((reg_b_layout[1], Target::Register(RegisterId::B)));
+    render_bus_connection(frame, BusConnection::Both, computer.control_links[&ControlLine::BI].get_state(), theme, reg_b_layout[0]);
     let reg_out_layout = Layout::default()
     .direction(Direction::Horizontal)
     .constraints(vec![
@@ -157,36 +176,104 @@ fn render_right(frame: &mut Frame, right_inner_layout: &Rc<[Rect]>, computer: &C
         Constraint::Percentage(10),
     ])
     .split(right_inner_layout[8]);
-    frame.render_widget(&computer.reg_out, reg_out_layout[1]);
-    render_h_link(frame, &*computer.control_links[&ControlLine::OI].borrow(), false, reg_out_layout[2]);
-    render_bus_connection(frame, BusConnection::Right, computer.control_links[&ControlLine::OI].borrow().get_state(),reg_out_layout[0]);
+    render_output_display(frame, &computer.reg_out, output_mode, theme, reg_out_layout[1]);
+    regions.push((reg_out_layout[1], Target::Register(RegisterId::OUT)));
+    render_h_link(frame, &computer.control_links[&ControlLine::OI], false, theme, reg_out_layout[2]);
+    regions.push((reg_out_layout[2], Target::Control(ControlLine::OI)));
+    render_bus_connection(frame, BusConnection::Right, computer.control_links[&ControlLine::OI].get_state(), theme, reg_out_layout[0]);
 }
 
-fn render(frame: &mut Frame, clock: &Rc<RefCell<Clock>>, computer: &Computer) {
-    let main_layout = Layout::default()
-    .direction(Direction::Horizontal)
-    .constraints(vec![
-        Constraint::Percentage(10),
-        Constraint::Percentage(65),
-        Constraint::Percentage(10),
-        Constraint::Percentage(25),
-    ])
-    .split(frame.area());
+// Smallest terminal sizes (columns, rows) for each screen layout
+const FULL_LAYOUT_SIZE: (u16, u16) = (140, 45);
+const COMPACT_LAYOUT_SIZE: (u16, u16) = (80, 24);
+
+// Screen layout picked from the terminal size
+#[derive(Copy, Clone, PartialEq)]
+pub enum ScreenLayout {
+    Full,
+    Compact,
+    TooSmall,
+}
+
+impl ScreenLayout {
+    pub fn for_area(area: Rect) -> Self {
+        let fits = |(width, height): (u16, u16)| area.width >= width && area.height >= height;
+        if fits(FULL_LAYOUT_SIZE) {
+            ScreenLayout::Full
+        } else if fits(COMPACT_LAYOUT_SIZE) {
+            ScreenLayout::Compact
+        } else {
+            ScreenLayout::TooSmall
+        }
+    }
+}
+
+/// Draws a whole frame and collects the clickable regions, independent of the terminal backend.
+pub fn render(frame: &mut Frame, clock: &Rc<RefCell<Clock>>, computer: &Computer, waveform: &Waveform, view: &View, regions: &mut Vec<(Rect, Target)>) {
+    let theme = &view.theme;
+    let screen = ScreenLayout::for_area(frame.area());
+    if screen == ScreenLayout::TooSmall {
+        render_too_small(frame, COMPACT_LAYOUT_SIZE, theme, frame.area());
+        return;
+    }
+    // The compact layout drops the logic analyser and the output log
+    let show_waveform = view.show_waveform && screen == ScreenLayout::Full;
+    let show_output_log = view.show_output_log && screen == ScreenLayout::Full;
+    let (computer_area, inspector_area) = if screen == ScreenLayout::Full {
+        let main_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![
+            Constraint::Percentage(10),
+            Constraint::Percentage(65),
+            Constraint::Percentage(10),
+            Constraint::Percentage(25),
+        ])
+        .split(frame.area());
+        (main_layout[1], main_layout[3])
+    } else {
+        let main_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![
+            Constraint::Percentage(70),
+            Constraint::Percentage(30),
+        ])
+        .split(frame.area());
+        (main_layout[0], main_layout[1])
+    };
     let inspector_layout = Layout::default()
     .direction(Direction::Vertical)
-    .constraints(vec![
-        Constraint::Percentage(75),
-        Constraint::Percentage(25),
-    ])
-    .split(main_layout[3]);
+    .constraints(if show_output_log {
+        vec![
+            Constraint::Percentage(55),
+            Constraint::Percentage(25),
+            Constraint::Percentage(20),
+        ]
+    } else {
+        vec![
+            Constraint::Percentage(80),
+            Constraint::Percentage(0),
+            Constraint::Percentage(20),
+        ]
+    })
+    .split(inspector_area);
     let computer_layout = Layout::default()
     .direction(Direction::Vertical)
-    .constraints(vec![
-        Constraint::Percentage(70),
-        Constraint::Percentage(10),
-        Constraint::Percentage(20),
-    ])
-    .split(main_layout[1]);
+    .constraints(if show_waveform {
+        vec![
+            Constraint::Percentage(55),
+            Constraint::Percentage(5),
+            Constraint::Percentage(15),
+            Constraint::Percentage(25),
+        ]
+    } else {
+        vec![
+            Constraint::Percentage(65),
+            Constraint::Percentage(5),
+            Constraint::Percentage(30),
+            Constraint::Percentage(0),
+        ]
+    })
+    .split(computer_area);
     let top_computer_layout = Layout::default()
     .direction(Direction::Horizontal)
     .constraints(vec![
@@ -224,14 +311,18 @@ fn render(frame: &mut Frame, clock: &Rc<RefCell<Clock>>, computer: &Computer) {
     ])
     .split(top_computer_layout[2]);
     // Left
-    render_left(frame, &left_inner_layout, clock, computer);
+    render_left(frame, &left_inner_layout, clock, computer, theme, regions);
     // Bus
-    frame.render_widget(&*computer.bus.borrow(), top_computer_layout[1]);
+    frame.render_widget(Themed::new(&computer.bus, theme), top_computer_layout[1]);
     // Right
-    render_right(frame, &right_inner_layout, computer);
+    render_right(frame, &right_inner_layout, computer, view.output_mode, theme, regions);
     // Inspector
-    render_ram_inspector(frame, &computer.ram, inspector_layout[0]);
-    frame.render_widget( &*clock.borrow(), inspector_layout[1]);
+    let ram_rows = render_ram_inspector(frame, &computer.ram, computer.controller.sequencer.get_decoder(), &view.inspector, &computer.breakpoints, theme, inspector_layout[0]);
+    regions.extend(ram_rows.into_iter().map(|(area, address)| (area, Target::RamCell(address))));
+    if show_output_log {
+        render_output_log(frame, computer.outputs(), view.output_mode, theme, inspector_layout[1]);
+    }
+    frame.render_widget(Themed::new(&*clock.borrow(), theme), inspector_layout[2]);
     // Controller
     let controller_layout = Layout::default()
     .direction(Direction::Vertical)
@@ -240,34 +331,128 @@ fn render(frame: &mut Frame, clock: &Rc<RefCell<Clock>>, computer: &Computer) {
         Constraint::Percentage(70),
     ])
     .split(computer_layout[2]);
-    render_all_links(frame, &computer.control_links, controller_layout[0]);
-    frame.render_widget(&computer.controller, controller_layout[1]);
+    // The selected line only matters while the lines are driven by hand
+    let selected = computer.is_manual().then(|| ControlLine::all()[view.control_cursor].clone());
+    let links = render_all_links(frame, &computer.control_links, selected, theme, controller_layout[0]);
+    regions.extend(links.into_iter().map(|(area, line)| (area, Target::Control(line))));
+    frame.render_widget(Themed::new(&computer.controller, theme), controller_layout[1]);
+    // Logic analyser
+    if show_waveform {
+        frame.render_widget(Themed::new(waveform, theme), computer_layout[3]);
+    }
+    // Overlays
+    if let Some(edit) = &view.register_edit {
+        render_register_edit(frame, edit, computer.read_register(edit.register), computer.register_width(edit.register), theme, frame.area());
+    }
+    if let Some((text, error)) = &view.notice {
+        render_notice(frame, text, *error, theme, frame.area());
+    }
+    if let Some(selected) = view.example_menu {
+        render_example_menu(frame, selected, theme, frame.area());
+    }
+    if view.show_help {
+        render_help(frame, &view.help, theme, frame.area());
+    }
+}
+
+// Screen areas reacting to the mouse
+#[derive(Clone, PartialEq)]
+pub enum Target {
+    Control(ControlLine),
+    RamCell(usize),
+    Register(RegisterId),
+}
+
+// Interface state driven by the keyboard
+pub struct View {
+    pub inspector: RamInspector,
+    pub output_mode: OutputMode,
+    pub show_help: bool,
+    pub show_waveform: bool,
+    pub show_output_log: bool,
+    pub register_edit: Option<RegisterEdit>,
+    /// Selected entry while the example menu is open
+    pub example_menu: Option<usize>,
+    pub control_cursor: usize,
+    /// Message shown until the next key, and whether it reports an error
+    pub notice: Option<(String, bool)>,
+    pub theme: Theme,
+    pub help: Vec<(String, String)>,
+}
+
+impl View {
+    pub fn new(help: Vec<(String, String)>, theme: Theme) -> Self {
+        Self {
+            inspector: RamInspector::new(),
+            output_mode: OutputMode::Unsigned,
+            show_help: false,
+            show_waveform: true,
+            show_output_log: true,
+            register_edit: None,
+            example_menu: None,
+            control_cursor: 0,
+            notice: None,
+            theme,
+            help,
+        }
+    }
 }
 
 pub struct Renderer {
-    terminal: Terminal<CrosstermBackend<Stdout>>
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    pub view: View,
+    pub regions: Vec<(Rect, Target)>,
 }
 
 impl Renderer {
-    pub fn new() -> Self {
-        color_eyre::install().unwrap();
-        let ren = Self {
-            terminal: ratatui::init()
-        };
-        return ren;
+    pub fn new(help: Vec<(String, String)>, theme: Theme) -> Self {
+        let terminal = ratatui::init();
+        set_panic_hook();
+        execute!(std::io::stdout(), EnableMouseCapture).unwrap();
+        Self {
+            terminal,
+            view: View::new(help, theme),
+            regions: Vec::new(),
+        }
     }
 
-    pub fn draw(&mut self, clock: &Rc<RefCell<Clock>>, computer: &Computer) {
+    pub fn draw(&mut self, clock: &Rc<RefCell<Clock>>, computer: &Computer, waveform: &Waveform) {
+        let view = &self.view;
+        let mut regions = Vec::new();
         self.terminal.draw(|f| {
             // Pass required arguments to the render logic here
-            render(f, clock, computer)
+            render(f, clock, computer, waveform, view, &mut regions)
         }).unwrap();
+        self.regions = regions;
     }
 
-    pub fn stop(&mut self) {
-        disable_raw_mode().unwrap();
-        execute!(self.terminal.backend_mut(), LeaveAlternateScreen).unwrap();
-        self.terminal.show_cursor().unwrap();
+    /// Topmost clickable element under a screen position.
+    pub fn target_at(&self, column: u16, row: u16) -> Option<Target> {
+        self.regions.iter()
+            .rev()
+            .find(|(area, _)| area.contains(Position { x: column, y: row }))
+            .map(|(_, target)| target.clone())
+    }
+
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        restore_terminal();
     }
+}
 
-}
\ No newline at end of file
+/// Leaves raw mode, mouse capture and the alternate screen, safe to call more than once.
+fn restore_terminal() {
+    let _ = execute!(std::io::stdout(), DisableMouseCapture);
+    ratatui::restore();
+}
+
+/// Restores the terminal before the previous hook prints the panic report.
+fn set_panic_hook() {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        hook(info);
+    }));
+}
@@ -0,0 +1,99 @@
+use std::str::FromStr;
+
+use ratatui::style::{Color, Modifier, Style};
+
+// Styles of the interface roles, every widget draws through a theme
+#[derive(Clone)]
+pub struct Theme {
+    pub name: &'static str,
+    /// Labels and neutral text
+    pub text: Style,
+    /// Asserted lines, register contents, running clock
+    pub active: Style,
+    /// Idle lines and secondary information
+    pub inactive: Style,
+    /// Halt, errors, breakpoints and lines held by the user
+    pub alert: Style,
+    /// Current microcode step
+    pub highlight: Style,
+    /// Cursor row in the RAM inspector and the example menu
+    pub selection: Style,
+    /// Seven segment digits
+    pub output: Style,
+}
+
+impl Theme {
+    pub fn default_theme() -> Self {
+        Self {
+            name: "default",
+            text: Style::default().fg(Color::White),
+            active: Style::default().fg(Color::Yellow),
+            inactive: Style::default().fg(Color::Gray),
+            alert: Style::default().fg(Color::Red),
+            highlight: Style::default().fg(Color::Black).bg(Color::Yellow),
+            selection: Style::default().bg(Color::DarkGray),
+            output: Style::default().fg(Color::Red),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            name: "high-contrast",
+            text: Style::default().fg(Color::White),
+            active: Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            inactive: Style::default().fg(Color::White),
+            alert: Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD),
+            highlight: Style::default().fg(Color::Black).bg(Color::White),
+            selection: Style::default().fg(Color::Black).bg(Color::LightCyan),
+            output: Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD),
+        }
+    }
+
+    /// Okabe-Ito orange and blue, which stay apart under every common colour vision deficiency.
+    pub fn colour_blind() -> Self {
+        let orange = Color::Rgb(230, 159, 0);
+        let blue = Color::Rgb(86, 180, 233);
+        Self {
+            name: "colour-blind",
+            text: Style::default().fg(Color::White),
+            active: Style::default().fg(orange),
+            inactive: Style::default().fg(Color::Gray),
+            alert: Style::default().fg(blue).add_modifier(Modifier::BOLD),
+            highlight: Style::default().fg(Color::Black).bg(orange),
+            selection: Style::default().fg(Color::Black).bg(blue),
+            output: Style::default().fg(blue),
+        }
+    }
+
+    /// Text attributes only, for projectors and terminals without colour.
+    pub fn monochrome() -> Self {
+        Self {
+            name: "monochrome",
+            text: Style::default(),
+            active: Style::default().add_modifier(Modifier::BOLD),
+            inactive: Style::default().add_modifier(Modifier::DIM),
+            alert: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            highlight: Style::default().add_modifier(Modifier::REVERSED),
+            selection: Style::default().add_modifier(Modifier::REVERSED),
+            output: Style::default().add_modifier(Modifier::BOLD),
+        }
+    }
+
+    pub fn all() -> [Theme; 4] {
+        [Theme::default_theme(), Theme::high_contrast(), Theme::colour_blind(), Theme::monochrome()]
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Theme::all()
+            .into_iter()
+            .find(|theme| theme.name == s)
+            .ok_or_else(|| {
+                let names = Theme::all().iter().map(|theme| theme.name).collect::<Vec<_>>();
+                format!("unknown theme '{}', expected one of {}", s, names.join(", "))
+            })
+    }
+}
@@ -0,0 +1,44 @@
+use std::collections::VecDeque;
+
+use crate::{bitvecutils::Word, computer::Computer, control::line::ControlLine};
+
+pub const WAVEFORM_HISTORY: usize = 64; /* Cycles */
+
+// State of the machine while the clock was high
+pub struct CycleSample {
+    pub controls: Vec<ControlLine>,
+    pub bus: Word,
+}
+
+// Logic analyser history of the last clock cycles
+pub struct Waveform {
+    capacity: usize,
+    pub samples: VecDeque<CycleSample>,
+}
+
+impl Waveform {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            samples: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn record(&mut self, computer: &Computer, clock_state: bool) {
+        // Control lines are only asserted while the clock is high
+        if !clock_state {
+            return;
+        }
+        let controls = ControlLine::all()
+            .into_iter()
+            .filter(|line| computer.control_links[line].get_state())
+            .collect();
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(CycleSample {
+            controls,
+            bus: computer.bus.read(),
+        });
+    }
+}
@@ -1,21 +1,39 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::collections::BTreeSet;
 
-use ratatui::{buffer::Buffer, layout::{Alignment, Constraint, Direction, Layout, Rect}, style::{Color, Style}, symbols::border, text::{Line, Span, Text}, widgets::{Block, Paragraph, Widget, Wrap}, Frame};
+use ratatui::{buffer::Buffer, layout::{Alignment, Constraint, Direction, Layout, Rect}, style::{Modifier, Style}, symbols::border, text::{Line, Span, Text}, widgets::{Block, Cell, Clear, Paragraph, Row, Table, Widget, Wrap}, Frame};
 
-use crate::{alu::ALU, bitvecutils::{bitvec_to_usize, BinaryDisplay}, bus::Bus, clock::Clock, config::{OPCODE_SIZE, WORD_SIZE}, control::{control::ControlLine, controller::Controller}, link::Link, memory::{memory::RAM, register::{RORegister, RWRegister}}, pc::ProgramCounter};
+use crate::{alu::ALUStatus, computer::OutputRecord, bitvecutils::{BinaryDisplay, Word}, bus::Bus, clock::{Clock, ClockMode}, config::WORD_SIZE, control::{line::ControlLine, controller::Controller, decoder::Decoder}, examples::EXAMPLES, link::{ControlLinks, Link}, memory::{ram::RAM, register::{RORegister, RWRegister}}, pc::{ProgramCounter, ADDRESS_SIZE}};
 
-impl Widget for &ProgramCounter {
+use super::{inspector::{RamInspector, RegisterEdit}, output::{format_output, segment_glyph, OutputMode}, theme::Theme, waveform::Waveform};
+
+// Widget drawn with the styles of a theme
+pub struct Themed<'a, T> {
+    pub widget: &'a T,
+    pub theme: &'a Theme,
+}
+
+impl<'a, T> Themed<'a, T> {
+    pub fn new(widget: &'a T, theme: &'a Theme) -> Self {
+        Self {
+            widget,
+            theme
+        }
+    }
+}
+
+impl Widget for Themed<'_, ProgramCounter> {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        let (pc, theme) = (self.widget, self.theme);
         let title = Line::from(" Program Counter ");
         let block = Block::bordered()
             .title(title.centered())
             .border_set(border::THICK);
 
-        let bindata = self.address.to_bin_string();
-        let decdata = bitvec_to_usize(&self.address);
+        let bindata = pc.address.to_bin_string(ADDRESS_SIZE);
+        let decdata = pc.address;
         let mut widgetlines = Vec::new();
-        widgetlines.push(Line::from(vec![Span::styled(format!("0x{:X} | {} | {}", decdata, bindata.clone(), decdata), Style::default().fg(Color::Yellow))]));
-        widgetlines.push(Line::from(vec![Span::styled(bindata.replace('0', "◯").replace('1', "●"), Style::default().fg(Color::Yellow))]));
+        widgetlines.push(Line::from(vec![Span::styled(format!("0x{:X} | {} | {}", decdata, bindata.clone(), decdata), theme.active)]));
+        widgetlines.push(Line::from(vec![Span::styled(bindata.replace('0', "◯").replace('1', "●"), theme.active)]));
 
         Paragraph::new(widgetlines)
             .centered()
@@ -24,18 +42,19 @@ impl Widget for &ProgramCounter {
     }
 }
 
-impl Widget for &RORegister {
+impl Widget for Themed<'_, RORegister> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let title = Line::from(format!(" {} ", self.name));
+        let (register, theme) = (self.widget, self.theme);
+        let title = Line::from(format!(" {} ", register.name));
         let block = Block::bordered()
             .title(title.centered())
             .border_set(border::THICK);
 
-            let bindata = self.data.to_bin_string();
-            let decdata = bitvec_to_usize(&self.data);
+            let bindata = register.data.to_bin_string(register.width());
+            let decdata = register.data;
             let mut widgetlines = Vec::new();
-            widgetlines.push(Line::from(vec![Span::styled(format!("0x{:X} | {} | {}", decdata, bindata.clone(), decdata), Style::default().fg(Color::Yellow))]));
-            widgetlines.push(Line::from(vec![Span::styled(bindata.replace('0', "◯").replace('1', "●"), Style::default().fg(Color::Yellow))]));
+            widgetlines.push(Line::from(vec![Span::styled(format!("0x{:X} | {} | {}", decdata, bindata.clone(), decdata), theme.active)]));
+            widgetlines.push(Line::from(vec![Span::styled(bindata.replace('0', "◯").replace('1', "●"), theme.active)]));
     
 
         Paragraph::new(widgetlines)
@@ -45,18 +64,77 @@ impl Widget for &RORegister {
     }
 }
 
-impl Widget for &RWRegister {
+pub fn render_output_display(frame: &mut Frame, register: &RORegister, mode: OutputMode, theme: &Theme, area: Rect) {
+    let title = Line::from(format!(" {} [{}] ", register.name, mode.label()));
+    let block = Block::bordered()
+        .title(title.centered())
+        .border_set(border::THICK);
+
+    let text = format_output(register.data, register.width(), mode);
+    let mut lines = Vec::new();
+    if area.height >= 5 {
+        // Big seven segment digits
+        for row in 0..3 {
+            let digits = text.chars()
+                .map(|c| segment_glyph(c)[row])
+                .collect::<Vec<_>>()
+                .join(" ");
+            lines.push(Line::from(Span::styled(digits, theme.output)));
+        }
+    } else {
+        // Not enough room for the glyphs, show the digits as text
+        lines.push(Line::from(Span::styled(text, theme.output)));
+    }
+
+    frame.render_widget(
+        Paragraph::new(lines)
+            .centered()
+            .block(block),
+        area
+    );
+}
+
+pub fn render_output_log<'a>(frame: &mut Frame, outputs: impl DoubleEndedIterator<Item = &'a OutputRecord> + ExactSizeIterator, mode: OutputMode, theme: &Theme, area: Rect) {
+    let title = Line::from(format!(" Output Log ({}) ", outputs.len()));
+    let block = Block::bordered()
+        .title(title.centered())
+        .border_set(border::THICK);
+
+    // Scroll so the latest values stay visible
+    let visible = area.height.saturating_sub(3) as usize;
+    let mut recent: Vec<&OutputRecord> = outputs.rev().take(visible).collect();
+    recent.reverse();
+    let mut lines = Vec::new();
+    lines.push(Line::from(vec![
+        Span::styled("CYCLE|VALUE", theme.text),
+    ]));
+    for record in recent {
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:>5}|", record.cycle), theme.text),
+            Span::styled(format_output(record.value, WORD_SIZE, mode).trim().to_string(), theme.active),
+        ]));
+    }
+
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(block),
+        area
+    );
+}
+
+impl Widget for Themed<'_, RWRegister> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let title = Line::from(format!(" {} ", self.name));
+        let (register, theme) = (self.widget, self.theme);
+        let title = Line::from(format!(" {} ", register.name));
         let block = Block::bordered()
             .title(title.centered())
             .border_set(border::THICK);
 
-        let bindata = self.data.to_bin_string();
-        let decdata = bitvec_to_usize(&self.data);
+        let bindata = register.data.to_bin_string(register.width());
+        let decdata = register.data;
         let mut widgetlines = Vec::new();
-        widgetlines.push(Line::from(vec![Span::styled(format!("0x{:X} | {} | {}", decdata, bindata.clone(), decdata), Style::default().fg(Color::Yellow))]));
-        widgetlines.push(Line::from(vec![Span::styled(bindata.replace('0', "◯").replace('1', "●"), Style::default().fg(Color::Yellow))]));
+        widgetlines.push(Line::from(vec![Span::styled(format!("0x{:X} | {} | {}", decdata, bindata.clone(), decdata), theme.active)]));
+        widgetlines.push(Line::from(vec![Span::styled(bindata.replace('0', "◯").replace('1', "●"), theme.active)]));
 
         Paragraph::new(widgetlines)
             .centered()
@@ -65,34 +143,66 @@ impl Widget for &RWRegister {
     }
 }
 
-impl Widget for &ALU {
+impl Widget for Themed<'_, ALUStatus> {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        let (alu, theme) = (self.widget, self.theme);
         let title = Line::from(" ALU ");
         let block = Block::bordered()
             .title(title.centered())
             .border_set(border::THICK);
 
-        let body_text = Text::from(vec![Line::from(vec![
-            Span::raw(""), // Plain text span
-        ])]);
+        let result = &alu.result;
+        let bindata = result.value.to_bin_string(WORD_SIZE);
+        let decdata = result.value;
+        let operation = if alu.subtracting { "A-B" } else { "A+B" };
+        let flag_style = |flag: bool| if flag { theme.active } else { theme.text };
+        let mut widgetlines = Vec::new();
+        widgetlines.push(Line::from(vec![Span::styled(format!("0x{:X} | {} | {}", decdata, bindata, decdata), theme.active)]));
+        widgetlines.push(Line::from(vec![
+            Span::styled(format!("{} ", operation), theme.text),
+            Span::styled(format!("SU:{} ", alu.subtracting as u8), flag_style(alu.subtracting)),
+            Span::styled(format!("CF:{} ", result.carry as u8), flag_style(result.carry)),
+            Span::styled(format!("ZF:{} ", result.zero as u8), flag_style(result.zero)),
+            // Latched by FI, read by the conditional jumps
+            Span::styled("FLAGS:", theme.text),
+            Span::styled("C", flag_style(alu.flags.carry)),
+            Span::styled("Z", flag_style(alu.flags.zero)),
+        ]));
 
-        Paragraph::new(body_text)
+        Paragraph::new(widgetlines)
             .centered()
             .block(block)
             .render(area, buf);
     }
 }
 
-impl Widget for &Clock {
+impl Widget for Themed<'_, Clock> {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        let (clock, theme) = (self.widget, self.theme);
         let title = Line::from(" Clock ");
         let block = Block::bordered()
             .title(title.centered())
             .border_set(border::THICK);
 
-        let body_text = Text::from(vec![Line::from(vec![
-            Span::raw("TICK"), // Plain text span
-        ])]);
+        let (mode, mode_style) = match clock.get_mode() {
+            ClockMode::Running => ("RUN", theme.active),
+            ClockMode::Paused => ("PAUSE", theme.text),
+            ClockMode::Halted => ("HALT", theme.alert),
+        };
+        let (level, level_style) = if clock.get_state() {
+            ("HIGH", theme.active)
+        } else {
+            ("LOW", theme.text)
+        };
+        let body_text = Text::from(vec![
+            Line::from(vec![
+                Span::styled(format!("{} Hz | ", clock.get_frequency()), theme.text),
+                Span::styled(level, level_style),
+                Span::styled(" | ", theme.text),
+                Span::styled(mode, mode_style),
+            ]),
+            Line::from(Span::styled(format!("Cycles: {} | Instr: {}", clock.cycles, clock.instructions), theme.text)),
+        ]);
 
         Paragraph::new(body_text)
             .centered()
@@ -119,35 +229,68 @@ impl Widget for &RAM {
     }
 }
 
-pub fn render_ram_inspector(frame: &mut Frame, ram: &RAM, area: Rect) {
-    let title = Line::from(" RAM Inspector ");
+/// Returns the screen row of each visible RAM cell.
+pub fn render_ram_inspector(frame: &mut Frame, ram: &RAM, decoder: &Decoder, inspector: &RamInspector, breakpoints: &BTreeSet<usize>, theme: &Theme, area: Rect) -> Vec<(Rect, usize)> {
+    let title = if inspector.program_mode {
+        Line::from(" RAM Inspector [PROGRAM] ")
+    } else {
+        Line::from(" RAM Inspector ")
+    };
     let block = Block::bordered()
         .title(title.centered())
         .border_set(border::THICK);
 
+    let inner_area = block.inner(area);
+    let mut rows = Vec::new();
     let mut lines = Vec::new();
     lines.push(Line::from(vec![
-        Span::styled("ADDRESS|DATA", Style::default().fg(Color::White)),
+        Span::styled(" ADDRESS|DATA", theme.text),
     ]));
     for addr in 0..ram.memory.len() {
-        let addr_color = if addr < (1 << (WORD_SIZE - OPCODE_SIZE)) {
-            if addr == bitvec_to_usize(&ram.mar.borrow().read()) {
-                Color::Red
+        let row = addr as u16 + 1;
+        if row < inner_area.height {
+            rows.push((Rect { x: inner_area.x, y: inner_area.y + row, width: inner_area.width, height: 1 }, addr));
+        }
+        let addr_style = if addr < (1 << ADDRESS_SIZE) {
+            if addr == ram.mar.read() as usize {
+                theme.alert
             } else {
-                Color::White
+                theme.text
             }
         } else {
-            Color::Gray
+            theme.inactive
+        };
+        let data_style = if addr < (1 << ADDRESS_SIZE) {
+            theme.active
+        } else {
+            theme.inactive
         };
-        let data_color = if addr < (1 << (WORD_SIZE - OPCODE_SIZE)) {
-            Color::Yellow
+        let row_style = if inspector.program_mode && addr == inspector.cursor {
+            theme.selection
         } else {
-            Color::Gray
+            Style::default()
         };
+        let mnemonic = decoder.disassemble(ram.memory[addr]).unwrap_or_default();
+        let breakpoint = if breakpoints.contains(&addr) { "●" } else { " " };
         lines.push(Line::from(vec![
-            Span::styled(format!("{:01$b}|", addr, WORD_SIZE), Style::default().fg(addr_color)),
-            Span::styled(ram.memory[addr].to_bin_string(), Style::default().fg(data_color)),
-        ]));
+            Span::styled(breakpoint, theme.alert),
+            Span::styled(format!("{:01$b}|", addr, WORD_SIZE), addr_style),
+            Span::styled(ram.memory[addr].to_bin_string(WORD_SIZE), data_style),
+            Span::styled(format!(" {}", mnemonic), theme.inactive),
+        ]).style(row_style));
+    }
+    if inspector.program_mode {
+        lines.push(Line::from(""));
+        match &inspector.input {
+            Some(input) => {
+                let style = if inspector.error { theme.alert } else { theme.active };
+                lines.push(Line::from(Span::styled(format!("> {}_", input), style)));
+            }
+            None => {
+                lines.push(Line::from(Span::styled("Enter:edit 0-7:toggle bit", theme.inactive)));
+                lines.push(Line::from(Span::styled("Right click:breakpoint", theme.inactive)));
+            }
+        }
     }
 
     frame.render_widget(
@@ -156,10 +299,12 @@ pub fn render_ram_inspector(frame: &mut Frame, ram: &RAM, area: Rect) {
         .block(block),
         area
     );
+    rows
 }
 
-impl Widget for &Bus {
+impl Widget for Themed<'_, Bus> {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        let (bus, theme) = (self.widget, self.theme);
         let title = Line::from("W Bus");
 
         // Create the block with the title centered horizontally
@@ -167,24 +312,24 @@ impl Widget for &Bus {
             .title(title.centered())
             .border_set(border::THICK);
 
-        let bindata = self.data.to_bin_string();
-        let decdata = bitvec_to_usize(&self.data);
+        let bindata = bus.data.to_bin_string(WORD_SIZE);
+        let decdata = bus.data;
         let mut widgetlines = Vec::new();
         
         // Add the formatted lines for bus data
         widgetlines.push(Line::from(vec![Span::styled(
             format!("0x{:X} | {} | {}", decdata, bindata.clone(), decdata),
-            Style::default().fg(Color::Yellow),
+            theme.active,
         )]));
         widgetlines.push(Line::from(vec![Span::styled(
             bindata.replace('0', "◯").replace('1', "●"),
-            Style::default().fg(Color::Yellow),
+            theme.active,
         )]));
 
         // Calculate the number of blank lines to add above and below the content
         let content_height = widgetlines.len() as u16;
-        let blank_lines_top = (area.height - content_height) / 2;
-        let blank_lines_bottom = area.height - content_height - blank_lines_top;
+        let blank_lines_top = area.height.saturating_sub(content_height) / 2;
+        let blank_lines_bottom = area.height.saturating_sub(content_height + blank_lines_top);
 
         // Create the lines with blank lines at the top and bottom
         let mut final_lines = vec![];
@@ -211,27 +356,227 @@ impl Widget for &Bus {
     }
 }
 
-impl Widget for &Controller {
+impl Widget for Themed<'_, Controller> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (controller, theme) = (self.widget, self.theme);
+        let title = if controller.is_enabled() {
+            Line::from(" Controller Sequencer ")
+        } else {
+            Line::from(" Controller Sequencer (DISABLED) ")
+        };
+        let block = Block::bordered()
+            .title(title.centered())
+            .border_set(border::THICK);
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let sequencer = &controller.sequencer;
+        let current_step = sequencer.get_step();
+        let fetch_microcode = sequencer.get_fetch_microcode();
+        let opcode = sequencer.get_opcode();
+
+        // Execute steps are only known once the instruction has been decoded
+        let mut steps: Vec<(String, &[ControlLine])> = fetch_microcode.iter()
+            .map(|controls| ("FETCH".to_string(), *controls))
+            .collect();
+        if let Some(opcode) = opcode {
+            steps.extend(sequencer.get_instruction_microcode().iter().map(|controls| (opcode.to_string(), *controls)));
+        }
+
+        let instruction = match opcode {
+            Some(opcode) => opcode.to_string(),
+            None => "...".to_string(),
+        };
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Length(1),
+                Constraint::Fill(1),
+            ])
+            .split(inner_area);
+        let status = if controller.is_enabled() {
+            Line::from(vec![
+                Span::styled("Instruction: ", theme.text),
+                Span::styled(instruction, theme.active),
+                Span::styled(format!(" | Step: T{}", current_step), theme.text),
+            ])
+        } else {
+            Line::from(vec![
+                Span::styled("Manual control", theme.alert),
+                Span::styled(format!(" | Step held at T{}", current_step), theme.text),
+            ])
+        };
+        Paragraph::new(status)
+            .centered()
+            .render(layout[0], buf);
+
+        let column_style = |step: usize| {
+            if step == current_step {
+                theme.highlight
+            } else {
+                theme.text
+            }
+        };
+        let header = Row::new(steps.iter().enumerate()
+            .map(|(step, _)| Cell::from(format!("T{}", step)).style(column_style(step))));
+        let phases = Row::new(steps.iter().enumerate()
+            .map(|(step, (phase, _))| Cell::from(phase.clone()).style(column_style(step))));
+        let controls = Row::new(steps.iter().enumerate()
+            .map(|(step, (_, controls))| {
+                let text = controls.iter().map(|control| control.to_string()).collect::<Vec<_>>().join(" ");
+                Cell::from(text).style(column_style(step))
+            }));
+        let widths = steps.iter().map(|_| Constraint::Fill(1)).collect::<Vec<_>>();
+
+        Table::new(vec![phases, controls], widths)
+            .header(header)
+            .column_spacing(1)
+            .render(layout[1], buf);
+    }
+}
+
+impl Widget for Themed<'_, Waveform> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let title = Line::from(" Controller Sequencer ");
+        let (waveform, theme) = (self.widget, self.theme);
+        let title = Line::from(" Logic Analyser ");
         let block = Block::bordered()
             .title(title.centered())
             .border_set(border::THICK);
 
-        // Render the block (bordered title section)
-        Paragraph::new(vec![Line::from("")]) // Empty body text for the block
+        let label_width = 5;
+        let hex_width = WORD_SIZE.div_ceil(4);
+        let cycle_width = (hex_width + 1).max(4);
+        let inner_width = area.width.saturating_sub(2) as usize;
+        let visible = inner_width.saturating_sub(label_width) / cycle_width;
+        let skip = waveform.samples.len().saturating_sub(visible);
+        let samples: Vec<_> = waveform.samples.iter().skip(skip).collect();
+
+        let mut lines = Vec::new();
+        // Clock
+        let mut clk_spans = vec![Span::styled(format!("{:<1$}", "CLK", label_width), theme.text)];
+        for _ in &samples {
+            let half = cycle_width / 2;
+            clk_spans.push(Span::styled(format!("{}{}", "▔".repeat(half), "▁".repeat(cycle_width - half)), theme.active));
+        }
+        lines.push(Line::from(clk_spans));
+        // Bus
+        let mut bus_spans = vec![Span::styled(format!("{:<1$}", "BUS", label_width), theme.text)];
+        for sample in &samples {
+            bus_spans.push(Span::styled(format!("{:<1$}", format!("{:01$X}", sample.bus, hex_width), cycle_width), theme.active));
+        }
+        lines.push(Line::from(bus_spans));
+        // Every control line asserted during the displayed window
+        for line in ControlLine::all() {
+            if !samples.iter().any(|sample| sample.controls.contains(&line)) {
+                continue;
+            }
+            let mut spans = vec![Span::styled(format!("{:<1$}", line.to_string(), label_width), theme.text)];
+            for sample in &samples {
+                if sample.controls.contains(&line) {
+                    spans.push(Span::styled("▔".repeat(cycle_width), theme.active));
+                } else {
+                    spans.push(Span::styled("▁".repeat(cycle_width), theme.inactive));
+                }
+            }
+            lines.push(Line::from(spans));
+        }
+
+        Paragraph::new(lines)
             .block(block)
             .render(area, buf);
     }
 }
 
-pub fn render_all_links(frame: &mut Frame, control_links: &HashMap<ControlLine, Rc<RefCell<Link>>>, area: Rect) {
-    let controls = control_links.keys().collect::<Vec<_>>();
+/// Clears and returns a `width` x `height` area centered over `area`, shrunk to fit.
+pub fn centered_popup(frame: &mut Frame, area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    let popup_area = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    frame.render_widget(Clear, popup_area);
+    popup_area
+}
+
+pub fn render_help(frame: &mut Frame, bindings: &[(String, String)], theme: &Theme, area: Rect) {
+    let title = Line::from(" Help ");
+    let block = Block::bordered()
+        .title(title.centered())
+        .border_set(border::THICK);
+
+    let mut lines = Vec::new();
+    for (keys, description) in bindings {
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:>12}  ", keys), theme.active),
+            Span::styled(description.clone(), theme.text),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Program mode", theme.text)));
+    for (keys, description) in [
+        ("Up, Down", "Select a RAM cell"),
+        ("0-7", "Toggle a bit of the cell"),
+        ("Enter", "Edit the cell (value or mnemonic)"),
+        ("Esc", "Cancel the edit or leave"),
+    ] {
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:>12}  ", keys), theme.active),
+            Span::styled(description, theme.text),
+        ]));
+    }
+
+    let popup_area = centered_popup(frame, area, 56, lines.len() as u16 + 2);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(block),
+        popup_area
+    );
+}
+
+pub fn render_notice(frame: &mut Frame, text: &str, error: bool, theme: &Theme, area: Rect) {
+    let style = if error { theme.alert } else { theme.active };
+    // Bottom line of the screen
+    let notice_area = Rect {
+        y: area.y + area.height.saturating_sub(1),
+        height: area.height.min(1),
+        ..area
+    };
+    frame.render_widget(Clear, notice_area);
+    frame.render_widget(
+        Paragraph::new(Line::from(Span::styled(text, style))),
+        notice_area
+    );
+}
+
+pub fn render_too_small(frame: &mut Frame, required: (u16, u16), theme: &Theme, area: Rect) {
+    let lines = vec![
+        Line::from(Span::styled("Terminal too small", theme.alert)),
+        Line::from(Span::styled(format!("{}x{}, needs {}x{}", area.width, area.height, required.0, required.1), theme.text)),
+    ];
+    let text_area = Rect {
+        y: area.y + area.height.saturating_sub(lines.len() as u16) / 2,
+        height: area.height.min(lines.len() as u16),
+        ..area
+    };
+    frame.render_widget(
+        Paragraph::new(lines)
+            .centered()
+            .wrap(Wrap { trim: true }),
+        text_area
+    );
+}
+
+/// Returns the screen area of each control line.
+pub fn render_all_links(frame: &mut Frame, control_links: &ControlLinks, selected: Option<ControlLine>, theme: &Theme, area: Rect) -> Vec<(Rect, ControlLine)> {
+    let controls = ControlLine::all();
     let num_controls = controls.len() as u16;
 
     // Guard against division by zero
     if num_controls == 0 {
-        return;
+        return Vec::new();
     }
 
     // Calculate the percentage for each control
@@ -250,46 +595,108 @@ pub fn render_all_links(frame: &mut Frame, control_links: &HashMap<ControlLine,
         .split(area);
 
     // Render each control in its respective layout cell
-    for (i, key) in controls.iter().enumerate() {
-        if let Some(link) = control_links.get(*key) {
-            let link = link.borrow(); // Borrow the Link instance
-            let color = if link.get_state() {
-                Color::Yellow
-            } else {
-                Color::White
-            };
-
-            let body_text = Text::from(vec![
-                Line::from(Span::styled(format!("{}", link.control), Style::default().fg(color))),
-                Line::from(Span::styled("|", Style::default().fg(color))),
-            ]);
-
-            // Render the paragraph inside the layout cell
-            frame.render_widget(
-                Paragraph::new(body_text).alignment(Alignment::Center), // Center the text within the widget
-                link_layout[i],
-            );
+    let mut areas = Vec::new();
+    for (i, key) in controls.into_iter().enumerate() {
+        let link = &control_links[&key];
+        // Lines forced by the user are drawn apart from the ones driven by the controller
+        let style = if link.get_forced().is_some() {
+            theme.alert
+        } else if link.get_state() {
+            theme.active
+        } else {
+            theme.text
+        };
+
+        let mut label_style = style;
+        if selected.as_ref() == Some(&key) {
+            label_style = label_style.add_modifier(Modifier::REVERSED);
         }
+        let body_text = Text::from(vec![
+            Line::from(Span::styled(format!("{}", link.control), label_style)),
+            // Forced lines show the level they are held at, even when the controller drives the other
+            Line::from(Span::styled(match link.get_forced() {
+                Some(true) => "1",
+                Some(false) => "0",
+                None => "|",
+            }, style)),
+        ]);
+
+        // Render the paragraph inside the layout cell
+        frame.render_widget(
+            Paragraph::new(body_text).alignment(Alignment::Center), // Center the text within the widget
+            link_layout[i],
+        );
+        areas.push((link_layout[i], key));
     }
+    areas
+}
+
+pub fn render_register_edit(frame: &mut Frame, edit: &RegisterEdit, current: Word, width: usize, theme: &Theme, area: Rect) {
+    let title = Line::from(format!(" Edit {} ", edit.register));
+    let block = Block::bordered()
+        .title(title.centered())
+        .border_set(border::THICK);
+
+    let style = if edit.error { theme.alert } else { theme.active };
+    let lines = vec![
+        Line::from(Span::styled(format!("Current: 0x{:X} | {}", current, current.to_bin_string(width)), theme.text)),
+        Line::from(Span::styled(format!("> {}_", edit.input), style)),
+    ];
+
+    let popup_area = centered_popup(frame, area, 40, 4);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(block),
+        popup_area
+    );
+}
+
+pub fn render_example_menu(frame: &mut Frame, selected: usize, theme: &Theme, area: Rect) {
+    let title = Line::from(" Examples ");
+    let block = Block::bordered()
+        .title(title.centered())
+        .title_bottom(Line::from(" Enter load, Esc cancel ").centered())
+        .border_set(border::THICK);
+
+    let lines = EXAMPLES.iter()
+        .enumerate()
+        .map(|(index, example)| {
+            let row_style = if index == selected { theme.selection } else { Style::default() };
+            Line::from(vec![
+                Span::styled(format!("{:<12}", example.name), theme.active),
+                Span::styled(example.description, theme.text),
+            ]).style(row_style)
+        })
+        .collect::<Vec<_>>();
+
+    let popup_area = centered_popup(frame, area, 56, lines.len() as u16 + 2);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(block),
+        popup_area
+    );
 }
 
-pub fn render_h_link(frame: &mut Frame, link: &Link, left: bool, area: Rect) {
+pub fn render_h_link(frame: &mut Frame, link: &Link, left: bool, theme: &Theme, area: Rect) {
+    if area.is_empty() {
+        return;
+    }
     let display_text = if left {
         format!("{}🭹", link.control)
     } else {
         format!("🭹{}", link.control)
     };
 
-    // Determine text color based on `link.get_state()`
-    let text_color = if link.get_state() {
-        Color::Yellow
+    // Determine text style based on `link.get_state()`
+    let text_style = if link.get_state() {
+        theme.active
     } else {
-        Color::White
+        theme.text
     };
 
     // Create the styled text
     let body_text = Text::from(vec![Line::from(vec![
-        Span::styled(display_text, Style::default().fg(text_color)),
+        Span::styled(display_text, text_style),
     ])]);
 
     // Determine alignment based on the `left` flag
@@ -319,26 +726,26 @@ pub enum BusConnection {
     Both
 }
 
-pub fn render_bus_connection(frame: &mut Frame, connection: BusConnection, state: bool, area: Rect) {
+pub fn render_bus_connection(frame: &mut Frame, connection: BusConnection, state: bool, theme: &Theme, area: Rect) {
     // Ensure at least 1 line in the center
     let center_y = area.height / 2; // Single center line
 
     // Determine the pattern based on connection type
     let pattern = match connection {
-        BusConnection::Left => format!("🭮{}", "█".repeat((area.width - 1) as usize)),
-        BusConnection::Right => format!("{}🭬", "█".repeat((area.width - 1) as usize)),
-        BusConnection::Both => format!("🭮{}🭬", "█".repeat((area.width - 2) as usize)),
+        BusConnection::Left => format!("🭮{}", "█".repeat(area.width.saturating_sub(1) as usize)),
+        BusConnection::Right => format!("{}🭬", "█".repeat(area.width.saturating_sub(1) as usize)),
+        BusConnection::Both => format!("🭮{}🭬", "█".repeat(area.width.saturating_sub(2) as usize)),
     };
 
     // Generate the lines with only the center row rendered
     let lines: Vec<Line> = (0..area.height)
         .map(|i| {
             if i == center_y {
-                // Check the state and apply yellow color if state is true
+                // Check the state and use the active style if state is true
                 let style = if state {
-                    Style::default().fg(Color::Yellow) // Yellow color
+                    theme.active
                 } else {
-                    Style::default() // Default style (no color change)
+                    Style::default()
                 };
 
                 // Draw the pattern at the center with the determined style
@@ -358,10 +765,9 @@ pub fn render_bus_connection(frame: &mut Frame, connection: BusConnection, state
 pub enum ICConnection {
     Up,
     Down,
-    Both
 }
 
-pub fn render_ic_connection(frame: &mut Frame, connection: ICConnection, state: bool, area: Rect) {
+pub fn render_ic_connection(frame: &mut Frame, connection: ICConnection, state: bool, theme: &Theme, area: Rect) {
     // Define the layout constraints (20% left, 70% center, 10% right)
     let constraints = vec![
         Constraint::Percentage(20), // 20% for the left margin
@@ -379,20 +785,19 @@ pub fn render_ic_connection(frame: &mut Frame, connection: ICConnection, state:
     let pattern = match connection {
         ICConnection::Up => vec!["██", "██"],
         ICConnection::Down => vec!["██", "██"],
-        ICConnection::Both => vec!["██", "██"],
     };
 
-    // Choose the color based on the state
-    let color = if state {
-        Color::Yellow
+    // Choose the style based on the state
+    let style = if state {
+        theme.active
     } else {
-        Color::Gray
+        theme.inactive
     };
 
     // Convert the pattern into styled lines
     let lines: Vec<Line> = pattern
         .into_iter()
-        .map(|symbol| Line::from(Span::styled(symbol, Style::default().fg(color))))
+        .map(|symbol| Line::from(Span::styled(symbol, style)))
         .collect();
 
     // Create the text and render the widget in the 70% area
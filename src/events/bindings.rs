@@ -0,0 +1,219 @@
+use std::{collections::HashMap, fmt, fs, str::FromStr};
+
+use crossterm::event::KeyCode;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Help,
+    Pause,
+    Step,
+    Reset,
+    SpeedUp,
+    SpeedDown,
+    SaveState,
+    LoadState,
+    ProgramMode,
+    OutputMode,
+    ToggleWaveform,
+    ToggleOutputLog,
+    ManualMode,
+    Examples,
+    DumpRam,
+}
+
+impl Action {
+    pub fn all() -> [Action; 16] {
+        [
+            Action::Quit, Action::Help, Action::Pause, Action::Step, Action::Reset,
+            Action::SpeedUp, Action::SpeedDown, Action::SaveState, Action::LoadState,
+            Action::ProgramMode, Action::OutputMode, Action::ToggleWaveform, Action::ToggleOutputLog,
+            Action::ManualMode, Action::Examples, Action::DumpRam,
+        ]
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit the emulator",
+            Action::Help => "Show or hide this help",
+            Action::Pause => "Pause or resume the clock",
+            Action::Step => "Single step one clock cycle",
+            Action::Reset => "Reset registers, keep RAM",
+            Action::SpeedUp => "Double the clock frequency",
+            Action::SpeedDown => "Halve the clock frequency",
+            Action::SaveState => "Save a machine snapshot",
+            Action::LoadState => "Restore the machine snapshot",
+            Action::ProgramMode => "Enter or leave program mode",
+            Action::OutputMode => "Cycle the output display mode",
+            Action::ToggleWaveform => "Show or hide the logic analyser",
+            Action::ToggleOutputLog => "Show or hide the output log",
+            Action::ManualMode => "Drive the control lines by hand",
+            Action::Examples => "Load an example program",
+            Action::DumpRam => "Write RAM to the dump file",
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Action::Quit => "quit",
+            Action::Help => "help",
+            Action::Pause => "pause",
+            Action::Step => "step",
+            Action::Reset => "reset",
+            Action::SpeedUp => "speed_up",
+            Action::SpeedDown => "speed_down",
+            Action::SaveState => "save_state",
+            Action::LoadState => "load_state",
+            Action::ProgramMode => "program_mode",
+            Action::OutputMode => "output_mode",
+            Action::ToggleWaveform => "toggle_waveform",
+            Action::ToggleOutputLog => "toggle_output_log",
+            Action::ManualMode => "manual_mode",
+            Action::Examples => "examples",
+            Action::DumpRam => "dump_ram",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for Action {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::all()
+            .into_iter()
+            .find(|action| action.to_string() == s)
+            .ok_or(())
+    }
+}
+
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let key = match name.to_lowercase().as_str() {
+        "esc" | "escape" => KeyCode::Esc,
+        "enter" => KeyCode::Enter,
+        "space" => KeyCode::Char(' '),
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        lower => {
+            let number = lower.strip_prefix('f')?.parse::<u8>().ok()?;
+            if !(1..=12).contains(&number) {
+                return None;
+            }
+            KeyCode::F(number)
+        }
+    };
+    Some(key)
+}
+
+pub fn key_name(key: &KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(number) => format!("F{}", number),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+// Key to action table
+pub struct KeyBindings {
+    bindings: HashMap<KeyCode, Action>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyBindings {
+    pub fn new() -> Self {
+        let mut bindings = HashMap::new();
+        bindings.insert(KeyCode::Esc, Action::Quit);
+        bindings.insert(KeyCode::Char('q'), Action::Quit);
+        bindings.insert(KeyCode::Char('?'), Action::Help);
+        bindings.insert(KeyCode::Char(' '), Action::Pause);
+        bindings.insert(KeyCode::Char('s'), Action::Step);
+        bindings.insert(KeyCode::Char('r'), Action::Reset);
+        bindings.insert(KeyCode::Char('+'), Action::SpeedUp);
+        bindings.insert(KeyCode::Char('='), Action::SpeedUp);
+        bindings.insert(KeyCode::Char('-'), Action::SpeedDown);
+        bindings.insert(KeyCode::F(5), Action::SaveState);
+        bindings.insert(KeyCode::F(9), Action::LoadState);
+        bindings.insert(KeyCode::Char('p'), Action::ProgramMode);
+        bindings.insert(KeyCode::Char('o'), Action::OutputMode);
+        bindings.insert(KeyCode::Char('w'), Action::ToggleWaveform);
+        bindings.insert(KeyCode::Char('l'), Action::ToggleOutputLog);
+        bindings.insert(KeyCode::Char('m'), Action::ManualMode);
+        bindings.insert(KeyCode::Char('e'), Action::Examples);
+        bindings.insert(KeyCode::Char('d'), Action::DumpRam);
+        Self {
+            bindings
+        }
+    }
+
+    /// Loads "key = action" lines on top of the defaults, an action bound in the file loses its default keys.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut entries = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, action) = line.split_once('=')
+                .ok_or(format!("{}:{}: expected 'key = action'", path, index + 1))?;
+            let key = parse_key(key.trim())
+                .ok_or(format!("{}:{}: unknown key '{}'", path, index + 1, key.trim()))?;
+            let action = action.trim().parse::<Action>()
+                .map_err(|_| format!("{}:{}: unknown action '{}'", path, index + 1, action.trim()))?;
+            entries.push((key, action));
+        }
+
+        let mut keybindings = Self::new();
+        for (_, action) in &entries {
+            keybindings.bindings.retain(|_, bound| bound != action);
+        }
+        for (key, action) in entries {
+            keybindings.bindings.insert(key, action);
+        }
+        Ok(keybindings)
+    }
+
+    pub fn get(&self, key: &KeyCode) -> Option<Action> {
+        self.bindings.get(key).copied()
+    }
+
+    /// Keys and description of every action, for the help overlay.
+    pub fn describe(&self) -> Vec<(String, String)> {
+        Action::all()
+            .into_iter()
+            .map(|action| {
+                let mut keys = self.bindings.iter()
+                    .filter(|(_, bound)| **bound == action)
+                    .map(|(key, _)| key_name(key))
+                    .collect::<Vec<_>>();
+                keys.sort();
+                (keys.join(", "), action.description().to_string())
+            })
+            .collect()
+    }
+}
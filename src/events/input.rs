@@ -0,0 +1,33 @@
+use std::{cell::RefCell, io::Error, rc::Rc, time::Duration};
+
+use crossterm::event::{self, Event, KeyEvent};
+
+use crate::{computer::Computer, examples::Example, display::renderer::Renderer, events::{bindings::{Action, KeyBindings}, keyboard::handle_key, mouse::handle_mouse}};
+
+pub enum Input {
+    /// Only the interface changed and needs a redraw
+    Interface,
+    /// Machine action for the main loop to run
+    Action(Action),
+    /// Example picked from the menu, to be loaded into RAM
+    LoadExample(&'static Example),
+}
+
+/// Waits up to `timeout` for a terminal event and applies it.
+pub fn handle_input(renderer: &Rc<RefCell<Renderer>>, computer: &mut Computer, bindings: &KeyBindings, timeout: Duration) -> Result<Option<Input>, Error> {
+    if event::poll(timeout)? {
+        match event::read()? {
+            Event::Key(KeyEvent { code, .. }) => {
+                return Ok(handle_key(&mut renderer.borrow_mut().view, computer, bindings, code));
+            }
+            Event::Mouse(mouse) => {
+                return Ok(handle_mouse(&mut renderer.borrow_mut(), computer, mouse));
+            }
+            Event::Resize(_, _) => {
+                return Ok(Some(Input::Interface));
+            }
+            _ => {}
+        }
+    }
+    Ok(None)
+}
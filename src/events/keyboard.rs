@@ -1,24 +1,120 @@
-use std::{cell::RefCell, collections::HashMap, io::Error, process::exit, rc::Rc};
+use crossterm::event::KeyCode;
 
-use crossterm::event::{self, Event, KeyCode, KeyEvent};
+use crate::{computer::Computer, config::WORD_SIZE, examples::EXAMPLES, control::line::ControlLine, display::renderer::View, events::{bindings::{Action, KeyBindings}, input::Input}};
 
-use crate::{control::control::ControlLine, display::renderer::Renderer, link::Link};
 
-
-pub fn handle_keyboard(renderer: &Rc<RefCell<Renderer>>, all_control_links: &HashMap<ControlLine, Rc<RefCell<Link>>>) -> Result<(), Error> {
-    if event::poll(std::time::Duration::from_secs(1))? {
-        if let Event::Key(KeyEvent { code, .. }) = event::read()? {
-            match code {
-                KeyCode::Esc => {
-                    renderer.borrow_mut().stop();
-                    std::process::exit(1);
+fn handle_program_mode(view: &mut View, computer: &mut Computer, bindings: &KeyBindings, code: KeyCode) {
+    let inspector = &mut view.inspector;
+    if inspector.input.is_some() {
+        // Editing the selected cell
+        match code {
+            KeyCode::Enter => inspector.commit_edit(&mut computer.ram, computer.controller.sequencer.get_decoder()),
+            KeyCode::Esc => inspector.cancel_edit(),
+            KeyCode::Backspace => {
+                if let Some(input) = &mut inspector.input {
+                    input.pop();
                 }
-                _ => {
-                    println!("Key pressed: {:?}", code);
-                    return Ok(());
+            }
+            KeyCode::Char(c) => {
+                if let Some(input) = &mut inspector.input {
+                    input.push(c);
                 }
             }
+            _ => {}
+        }
+        return;
+    }
+    match code {
+        KeyCode::Esc => inspector.toggle_program_mode(),
+        KeyCode::Up => inspector.move_cursor(-1, &computer.ram),
+        KeyCode::Down => inspector.move_cursor(1, &computer.ram),
+        KeyCode::Enter => inspector.start_edit(),
+        KeyCode::Char(c) if c.is_ascii_digit() => {
+            let bit = c.to_digit(10).unwrap() as usize;
+            if bit < WORD_SIZE {
+                computer.ram.toggle_bit(inspector.cursor, bit);
+            }
+        }
+        _ => {
+            if bindings.get(&code) == Some(Action::ProgramMode) {
+                inspector.toggle_program_mode();
+            }
+        }
+    }
+}
+
+fn handle_register_edit(view: &mut View, computer: &mut Computer, code: KeyCode) {
+    if let Some(edit) = &mut view.register_edit {
+        match code {
+            KeyCode::Enter if edit.commit(computer) => view.register_edit = None,
+            KeyCode::Esc => view.register_edit = None,
+            KeyCode::Backspace => {
+                edit.input.pop();
+            }
+            KeyCode::Char(c) => edit.input.push(c),
+            _ => {}
+        }
+    }
+}
+
+fn handle_example_menu(view: &mut View, code: KeyCode) -> Input {
+    if let Some(selected) = view.example_menu {
+        match code {
+            KeyCode::Up => view.example_menu = Some((selected + EXAMPLES.len() - 1) % EXAMPLES.len()),
+            KeyCode::Down => view.example_menu = Some((selected + 1) % EXAMPLES.len()),
+            KeyCode::Enter => {
+                view.example_menu = None;
+                return Input::LoadExample(&EXAMPLES[selected]);
+            }
+            KeyCode::Esc => view.example_menu = None,
+            _ => {}
         }
     }
-    Ok(())
-}
\ No newline at end of file
+    Input::Interface
+}
+
+fn handle_manual_mode(view: &mut View, computer: &mut Computer, code: KeyCode) -> bool {
+    let lines = ControlLine::all();
+    match code {
+        KeyCode::Left => view.control_cursor = (view.control_cursor + lines.len() - 1) % lines.len(),
+        KeyCode::Right => view.control_cursor = (view.control_cursor + 1) % lines.len(),
+        KeyCode::Enter => computer.toggle_user_control(&lines[view.control_cursor]),
+        _ => return false,
+    }
+    true
+}
+
+/// Applies interface actions to the view, machine actions are left to the caller.
+pub fn handle_key(view: &mut View, computer: &mut Computer, bindings: &KeyBindings, code: KeyCode) -> Option<Input> {
+    view.notice = None;
+    if view.show_help {
+        // Any key closes the overlay
+        view.show_help = false;
+        return Some(Input::Interface);
+    }
+    if view.register_edit.is_some() {
+        handle_register_edit(view, computer, code);
+        return Some(Input::Interface);
+    }
+    if view.example_menu.is_some() {
+        return Some(handle_example_menu(view, code));
+    }
+    if view.inspector.program_mode {
+        handle_program_mode(view, computer, bindings, code);
+        return Some(Input::Interface);
+    }
+    if computer.is_manual() && handle_manual_mode(view, computer, code) {
+        return Some(Input::Interface);
+    }
+    let action = bindings.get(&code)?;
+    match action {
+        Action::Help => view.show_help = true,
+        Action::ProgramMode => view.inspector.toggle_program_mode(),
+        Action::OutputMode => view.output_mode = view.output_mode.next(),
+        Action::ToggleWaveform => view.show_waveform = !view.show_waveform,
+        Action::ToggleOutputLog => view.show_output_log = !view.show_output_log,
+        Action::Examples => view.example_menu = Some(0),
+        _ => return Some(Input::Action(action)),
+    }
+    Some(Input::Interface)
+}
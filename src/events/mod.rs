@@ -1 +1,4 @@
-pub mod keyboard;
\ No newline at end of file
+pub mod bindings;
+pub mod input;
+pub mod keyboard;
+pub mod mouse;
\ No newline at end of file
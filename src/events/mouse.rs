@@ -0,0 +1,29 @@
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+
+use crate::{computer::Computer, display::{inspector::RegisterEdit, renderer::{Renderer, Target}}, events::input::Input};
+
+pub fn handle_mouse(renderer: &mut Renderer, computer: &mut Computer, event: MouseEvent) -> Option<Input> {
+    // Popups keep the focus until they are closed from the keyboard
+    if renderer.view.show_help || renderer.view.register_edit.is_some() || renderer.view.example_menu.is_some() || renderer.view.inspector.input.is_some() {
+        return None;
+    }
+    let button = match event.kind {
+        MouseEventKind::Down(button) => button,
+        _ => return None,
+    };
+    let target = renderer.target_at(event.column, event.row)?;
+    let view = &mut renderer.view;
+    match (target, button) {
+        (Target::Control(line), MouseButton::Left) => computer.toggle_user_control(&line),
+        (Target::RamCell(address), MouseButton::Left) => {
+            if !view.inspector.program_mode {
+                view.inspector.toggle_program_mode();
+            }
+            view.inspector.cursor = address;
+        }
+        (Target::RamCell(address), MouseButton::Right) => computer.toggle_breakpoint(address),
+        (Target::Register(register), MouseButton::Left) => view.register_edit = Some(RegisterEdit::new(register)),
+        _ => return None,
+    }
+    Some(Input::Interface)
+}
@@ -0,0 +1,96 @@
+use crate::{bitvecutils::{parse_word, Word}, config::{RAM_SIZE, WORD_SIZE}, control::decoder::Decoder};
+
+// Program shipped in the examples directory, as source and assembled RAM image
+pub struct Example {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub source: &'static str,
+    pub image: &'static [u8],
+}
+
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "count_up",
+        description: "Count from 0 to 255, then halt",
+        source: include_str!("../examples/count_up.asm"),
+        image: include_bytes!("../examples/count_up.bin"),
+    },
+    Example {
+        name: "count_down",
+        description: "Count from 255 down to 0, then halt",
+        source: include_str!("../examples/count_down.asm"),
+        image: include_bytes!("../examples/count_down.bin"),
+    },
+    Example {
+        name: "fibonacci",
+        description: "Fibonacci numbers up to 233",
+        source: include_str!("../examples/fibonacci.asm"),
+        image: include_bytes!("../examples/fibonacci.bin"),
+    },
+    Example {
+        name: "multiply",
+        description: "17 x 15 by repeated addition",
+        source: include_str!("../examples/multiply.asm"),
+        image: include_bytes!("../examples/multiply.bin"),
+    },
+    Example {
+        name: "prime",
+        description: "Prime test of 97 by repeated subtraction",
+        source: include_str!("../examples/prime.asm"),
+        image: include_bytes!("../examples/prime.bin"),
+    },
+    Example {
+        name: "bounce",
+        description: "Count up to 255 and back down, forever",
+        source: include_str!("../examples/bounce.asm"),
+        image: include_bytes!("../examples/bounce.bin"),
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Example> {
+    EXAMPLES.iter().find(|example| example.name == name)
+}
+
+/// Assembles "address: MNEMONIC [operand]" or "address: value" lines, text after ';' is a comment.
+/// Cells without a line are left at 0.
+pub fn assemble(source: &str) -> Result<Vec<Word>, String> {
+    let decoder = Decoder::new();
+    let mut memory = vec![0; RAM_SIZE];
+    for (index, line) in source.lines().enumerate() {
+        let line = line.split(';').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (address, item) = line.split_once(':')
+            .ok_or(format!("line {}: expected 'address: instruction'", index + 1))?;
+        let address = address.trim().parse::<usize>()
+            .ok()
+            .filter(|&address| address < RAM_SIZE)
+            .ok_or(format!("line {}: invalid address '{}'", index + 1, address.trim()))?;
+        memory[address] = parse_word(item, WORD_SIZE)
+            .or_else(|| decoder.assemble(item))
+            .ok_or(format!("line {}: invalid instruction or value '{}'", index + 1, item.trim()))?;
+    }
+    Ok(memory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assemble_places_instructions_and_values() {
+        let memory = assemble("; comment\n0: LDA 14 ; load\n1: OUT\n\n14: 0x2A\n").unwrap();
+        assert_eq!(memory.len(), RAM_SIZE);
+        assert_eq!(memory[0], 0x1E);
+        assert_eq!(memory[1], 0x50);
+        assert_eq!(memory[14], 42);
+    }
+
+    #[test]
+    fn assemble_reports_the_line() {
+        assert_eq!(assemble("0: OUT\n1: FOO 3").unwrap_err(), "line 2: invalid instruction or value 'FOO 3'");
+        assert!(assemble("16: OUT").is_err());
+        assert!(assemble("OUT").is_err());
+    }
+}
@@ -0,0 +1,487 @@
+use std::{fmt, fs, path::Path, str::FromStr};
+
+use crate::{bitvecutils::{convert_ramdump_to_words, convert_words_to_ramdump, get_bits, mask, BinaryDisplay, Word}, config::{OPCODE_SIZE, RAM_SIZE, WORD_SIZE}, control::decoder::{Decoder, OpCode}, pc::ADDRESS_SIZE};
+
+// Bytes of an Intel HEX image per word, least significant first
+const HEX_WORD_BYTES: usize = WORD_SIZE.div_ceil(8);
+
+// Largest image accepted, guards against huge addresses and run lengths
+const MAX_WORDS: usize = 1 << 16;
+
+// File formats of RAM images
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Words packed back to back, least significant bit first
+    Raw,
+    /// Intel HEX records, as read and written by EEPROM programmers
+    IntelHex,
+    /// Logisim "v2.0 raw" memory image
+    Logisim,
+    /// One hexadecimal word per line
+    Hex,
+    /// One binary word per line
+    Binary,
+}
+
+impl ImageFormat {
+    pub fn all() -> [ImageFormat; 5] {
+        [ImageFormat::Raw, ImageFormat::IntelHex, ImageFormat::Logisim, ImageFormat::Hex, ImageFormat::Binary]
+    }
+
+    /// Format written for a file extension: bin, hex, ihex, ihx or txt.
+    pub fn from_extension(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "bin" => Some(ImageFormat::Raw),
+            "hex" | "ihex" | "ihx" => Some(ImageFormat::IntelHex),
+            "txt" => Some(ImageFormat::Hex),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format of a file from its content, `.bin` files are always raw.
+    pub fn detect(path: &str, content: &[u8]) -> Self {
+        // Raw programs can happen to look like text
+        if ImageFormat::from_extension(path) == Some(ImageFormat::Raw) {
+            return ImageFormat::Raw;
+        }
+        let Ok(text) = std::str::from_utf8(content) else {
+            return ImageFormat::Raw;
+        };
+        if text.lines().next().map(str::trim) == Some("v2.0 raw") {
+            return ImageFormat::Logisim;
+        }
+        let is_binary = |token: &str| token.len() == WORD_SIZE && token.chars().all(|c| c == '0' || c == '1');
+        match text.trim_start().chars().next() {
+            None => ImageFormat::Raw,
+            Some(':') => ImageFormat::IntelHex,
+            Some(_) if tokens(text).all(is_binary) => ImageFormat::Binary,
+            Some(_) if read_text(text, 16).is_ok() => ImageFormat::Hex,
+            _ => ImageFormat::Raw,
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ImageFormat::Raw => "raw",
+            ImageFormat::IntelHex => "intel-hex",
+            ImageFormat::Logisim => "logisim",
+            ImageFormat::Hex => "hex-text",
+            ImageFormat::Binary => "binary",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ImageFormat::all()
+            .into_iter()
+            .find(|format| format.to_string() == s)
+            .ok_or_else(|| {
+                let names = ImageFormat::all().iter().map(|format| format.to_string()).collect::<Vec<_>>();
+                format!("unknown image format '{}', expected one of {}", s, names.join(", "))
+            })
+    }
+}
+
+/// Decodes every word of an image, words may be wider than `WORD_SIZE` and the image larger than RAM.
+pub fn read_image(content: &[u8], format: ImageFormat) -> Result<Vec<Word>, String> {
+    if format == ImageFormat::Raw {
+        return Ok(convert_ramdump_to_words(content, WORD_SIZE, content.len() * 8 / WORD_SIZE));
+    }
+    let text = std::str::from_utf8(content).map_err(|_| format!("not a {} image, the file is not text", format))?;
+    match format {
+        ImageFormat::IntelHex => read_intel_hex(text),
+        ImageFormat::Logisim => read_logisim(text),
+        ImageFormat::Hex => read_text(text, 16),
+        _ => read_text(text, 2),
+    }
+}
+
+pub fn write_image(memory: &[Word], format: ImageFormat) -> Vec<u8> {
+    match format {
+        ImageFormat::Raw => convert_words_to_ramdump(memory, WORD_SIZE),
+        ImageFormat::IntelHex => write_intel_hex(memory).into_bytes(),
+        ImageFormat::Logisim => write_logisim(memory).into_bytes(),
+        ImageFormat::Hex => memory.iter()
+            .map(|word| format!("{:01$X}\n", word, WORD_SIZE.div_ceil(4)))
+            .collect::<String>()
+            .into_bytes(),
+        ImageFormat::Binary => memory.iter()
+            .map(|word| format!("{}\n", word.to_bin_string(WORD_SIZE)))
+            .collect::<String>()
+            .into_bytes(),
+    }
+}
+
+/// Reads an image file, detecting its format unless one is given.
+pub fn load(path: &str, format: Option<ImageFormat>) -> Result<(ImageFormat, Vec<Word>), String> {
+    let content = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    let format = format.unwrap_or_else(|| ImageFormat::detect(path, &content));
+    let memory = read_image(&content, format).map_err(|e| format!("{}: {}", path, e))?;
+    Ok((format, memory))
+}
+
+/// Format `save` writes to `path`, checked before a run so a bad `--dump` doesn't lose the results at the end.
+pub fn save_format(path: &str, format: Option<ImageFormat>) -> Result<ImageFormat, String> {
+    let format = format.or_else(|| ImageFormat::from_extension(path))
+        .ok_or(format!("{}: unknown extension, pick an image format", path))?;
+    let directory = Path::new(path).parent().filter(|directory| !directory.as_os_str().is_empty());
+    if directory.is_some_and(|directory| !directory.is_dir()) {
+        return Err(format!("{}: no such directory", path));
+    }
+    Ok(format)
+}
+
+/// Writes an image file, in the format of its extension unless one is given.
+pub fn save(path: &str, memory: &[Word], format: Option<ImageFormat>) -> Result<ImageFormat, String> {
+    let format = save_format(path, format)?;
+    fs::write(path, write_image(memory, format)).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(format)
+}
+
+// Problem found in an image before it is loaded into RAM
+#[derive(Debug, PartialEq, Eq)]
+pub enum Finding {
+    /// Non-zero words past the end of RAM, they would be lost
+    Truncated { words: usize },
+    /// Zero words past the end of RAM, dropped without loss
+    Padding { words: usize },
+    /// Word with bits above `WORD_SIZE`, they would be lost
+    Wide { address: usize, value: Word },
+    /// Reachable instruction with an opcode the decoder doesn't know, it runs as a NOP
+    UndefinedOpcode { address: usize, opcode: Word },
+}
+
+impl Finding {
+    pub fn is_error(&self) -> bool {
+        matches!(self, Finding::Truncated { .. } | Finding::Wide { .. })
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::Truncated { words } => write!(f, "{} words past the end of the {} words of RAM are not zero", words, RAM_SIZE),
+            Finding::Padding { words } => write!(f, "{} zero words past the end of RAM are ignored", words),
+            Finding::Wide { address, value } => write!(f, "word 0x{:X} at address {} is wider than {} bits", value, address, WORD_SIZE),
+            Finding::UndefinedOpcode { address, opcode } => write!(f, "undefined opcode {} at address {} runs as NOP", opcode, address),
+        }
+    }
+}
+
+// Summary of an image, see validate
+#[derive(Debug)]
+pub struct ImageReport {
+    /// Words up to the last non-zero one within RAM
+    pub used: usize,
+    /// Instructions reachable from address 0
+    pub instructions: usize,
+    pub findings: Vec<Finding>,
+}
+
+impl ImageReport {
+    pub fn has_errors(&self) -> bool {
+        self.findings.iter().any(Finding::is_error)
+    }
+
+    pub fn summary(&self) -> String {
+        format!("{} of {} words used, {} instructions decoded", self.used, RAM_SIZE, self.instructions)
+    }
+}
+
+/// Checks an image against the size of RAM and words, and decodes the instructions reachable from address 0.
+pub fn validate(memory: &[Word]) -> ImageReport {
+    let decoder = Decoder::new();
+    let mut findings = Vec::new();
+    let ram = &memory[..memory.len().min(RAM_SIZE)];
+    if memory.len() > RAM_SIZE {
+        let words = memory.len() - RAM_SIZE;
+        if memory[RAM_SIZE..].iter().any(|&word| word != 0) {
+            findings.push(Finding::Truncated { words });
+        } else {
+            findings.push(Finding::Padding { words });
+        }
+    }
+    for (address, &value) in ram.iter().enumerate() {
+        if value > mask(WORD_SIZE) {
+            findings.push(Finding::Wide { address, value });
+        }
+    }
+
+    // Follow every path of the program, data is never reached unless the program runs into it
+    let mut reached = [false; 1 << ADDRESS_SIZE];
+    let mut pending = vec![0];
+    let mut undefined = Vec::new();
+    while let Some(address) = pending.pop() {
+        if reached[address] {
+            continue;
+        }
+        reached[address] = true;
+        let word = ram.get(address).copied().unwrap_or_default() & mask(WORD_SIZE);
+        let opcode = get_bits(word, ADDRESS_SIZE, OPCODE_SIZE);
+        let target = get_bits(word, 0, ADDRESS_SIZE) as usize;
+        let next = (address + 1) & mask(ADDRESS_SIZE) as usize;
+        match decoder.decode_opcode(opcode) {
+            Some(OpCode::HLT) => {}
+            Some(OpCode::JMP) => pending.push(target),
+            Some(OpCode::JC | OpCode::JZ) => pending.extend([target, next]),
+            Some(_) => pending.push(next),
+            None => {
+                undefined.push((address, opcode));
+                pending.push(next);
+            }
+        }
+    }
+    undefined.sort();
+    findings.extend(undefined.into_iter().map(|(address, opcode)| Finding::UndefinedOpcode { address, opcode }));
+
+    ImageReport {
+        used: ram.iter().rposition(|&word| word != 0).map_or(0, |last| last + 1),
+        instructions: reached.iter().filter(|&&reached| reached).count(),
+        findings,
+    }
+}
+
+/// Whitespace separated tokens, '#' starts a comment.
+fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .flat_map(|line| line.split('#').next().unwrap_or_default().split_whitespace())
+}
+
+fn read_text(text: &str, radix: u32) -> Result<Vec<Word>, String> {
+    let prefix = if radix == 16 { "0x" } else { "0b" };
+    let memory = tokens(text)
+        .map(|token| {
+            let digits = token.strip_prefix(prefix).unwrap_or(token).replace('_', "");
+            Word::from_str_radix(&digits, radix).map_err(|_| format!("invalid word '{}'", token))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if memory.len() > MAX_WORDS {
+        return Err(format!("more than {} words", MAX_WORDS));
+    }
+    Ok(memory)
+}
+
+fn read_logisim(text: &str) -> Result<Vec<Word>, String> {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some("v2.0 raw") {
+        return Err("missing the 'v2.0 raw' header".to_string());
+    }
+    let mut memory = Vec::new();
+    for token in tokens(&lines.collect::<Vec<_>>().join("\n")) {
+        // Runs are written as count*value
+        let (count, value) = match token.split_once('*') {
+            Some((count, value)) => (count.parse::<usize>().map_err(|_| format!("invalid run '{}'", token))?, value),
+            None => (1, token),
+        };
+        let value = Word::from_str_radix(value, 16).map_err(|_| format!("invalid word '{}'", token))?;
+        if count > MAX_WORDS - memory.len() {
+            return Err(format!("more than {} words", MAX_WORDS));
+        }
+        memory.extend(std::iter::repeat_n(value, count));
+    }
+    Ok(memory)
+}
+
+fn write_logisim(memory: &[Word]) -> String {
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < memory.len() {
+        let run = memory[index..].iter().take_while(|&&word| word == memory[index]).count();
+        // Logisim only shortens runs of four and more
+        if run >= 4 {
+            tokens.push(format!("{}*{:x}", run, memory[index]));
+        } else {
+            tokens.extend(std::iter::repeat_n(format!("{:x}", memory[index]), run));
+        }
+        index += run;
+    }
+    let mut text = "v2.0 raw\n".to_string();
+    for line in tokens.chunks(8) {
+        text.push_str(&line.join(" "));
+        text.push('\n');
+    }
+    text
+}
+
+fn read_intel_hex(text: &str) -> Result<Vec<Word>, String> {
+    let mut bytes = Vec::new();
+    let mut base = 0;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: &str| format!("line {}: {}", index + 1, message);
+        let record = line.strip_prefix(':').ok_or_else(|| error("expected a record starting with ':'"))?;
+        if !record.is_ascii() || record.len() % 2 != 0 {
+            return Err(error("invalid hex digits"));
+        }
+        let data = (0..record.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&record[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| error("invalid hex digits"))?;
+        if data.len() < 5 || data.len() != data[0] as usize + 5 {
+            return Err(error("record length does not match its byte count"));
+        }
+        if data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return Err(error("checksum mismatch"));
+        }
+        let address = (data[1] as usize) << 8 | data[2] as usize;
+        let payload = &data[4..data.len() - 1];
+        let segment = || (payload.len() == 2).then(|| (payload[0] as usize) << 8 | payload[1] as usize);
+        match data[3] {
+            0x00 => {
+                let start = base + address;
+                if start + payload.len() > MAX_WORDS * HEX_WORD_BYTES {
+                    return Err(error("address beyond the largest image"));
+                }
+                if bytes.len() < start + payload.len() {
+                    bytes.resize(start + payload.len(), 0);
+                }
+                bytes[start..start + payload.len()].copy_from_slice(payload);
+            }
+            0x01 => break,
+            0x02 => base = segment().ok_or_else(|| error("invalid segment address"))? << 4,
+            0x04 => base = segment().ok_or_else(|| error("invalid linear address"))? << 16,
+            // Start addresses mean nothing to the SAP-1
+            0x03 | 0x05 => {}
+            kind => return Err(error(&format!("unknown record type {:02X}", kind))),
+        }
+    }
+    Ok(bytes.chunks(HEX_WORD_BYTES)
+        .map(|chunk| chunk.iter().rev().fold(0, |word, &byte| word << 8 | byte as Word))
+        .collect())
+}
+
+fn write_intel_hex(memory: &[Word]) -> String {
+    let bytes = memory.iter()
+        .flat_map(|word| (0..HEX_WORD_BYTES).map(move |i| (word >> (8 * i)) as u8))
+        .collect::<Vec<_>>();
+    let mut text = String::new();
+    for (index, data) in bytes.chunks(16).enumerate() {
+        write_record(&mut text, 0x00, (index * 16) as u16, data);
+    }
+    write_record(&mut text, 0x01, 0, &[]);
+    text
+}
+
+fn write_record(text: &mut String, kind: u8, address: u16, data: &[u8]) {
+    let mut record = vec![data.len() as u8, (address >> 8) as u8, address as u8, kind];
+    record.extend_from_slice(data);
+    let checksum = record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)).wrapping_neg();
+    record.push(checksum);
+    text.push(':');
+    text.extend(record.iter().map(|byte| format!("{:02X}", byte)));
+    text.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: [Word; 16] = [0x1E, 0x2F, 0x50, 0xF0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 28, 14];
+
+    #[test]
+    fn every_format_round_trips() {
+        for format in ImageFormat::all() {
+            let content = write_image(&PROGRAM, format);
+            assert_eq!(read_image(&content, format).unwrap(), PROGRAM, "{}", format);
+            assert_eq!(ImageFormat::detect("program", &content), format, "{}", format);
+        }
+    }
+
+    #[test]
+    fn intel_hex_matches_a_programmer_file() {
+        let text = String::from_utf8(write_image(&PROGRAM, ImageFormat::IntelHex)).unwrap();
+        assert_eq!(text, ":100000001E2F50F0000000000000000000001C0E39\n:00000001FF\n");
+        assert_eq!(read_image(b":03000400010203F3\n:00000001FF\n", ImageFormat::IntelHex).unwrap(), vec![0, 0, 0, 0, 1, 2, 3]);
+        // Segment address records move the data by 16 bytes per segment
+        let memory = read_image(b":020000020001FB\n:03000400010203F3\n", ImageFormat::IntelHex).unwrap();
+        assert_eq!(memory.len(), 23);
+        assert_eq!(memory[20..], [1, 2, 3]);
+    }
+
+    #[test]
+    fn intel_hex_rejects_a_bad_checksum() {
+        let error = read_image(b":100000001E2F50F0000000000000000000001C0E38\n", ImageFormat::IntelHex).unwrap_err();
+        assert_eq!(error, "line 1: checksum mismatch");
+        assert!(read_image(b"1E2F", ImageFormat::IntelHex).is_err());
+    }
+
+    #[test]
+    fn logisim_expands_runs() {
+        let text = String::from_utf8(write_image(&PROGRAM, ImageFormat::Logisim)).unwrap();
+        assert_eq!(text, "v2.0 raw\n1e 2f 50 f0 10*0 1c e\n");
+        let memory = read_image(b"v2.0 raw\n# comment\n3*7 a\n", ImageFormat::Logisim).unwrap();
+        assert_eq!(memory, vec![7, 7, 7, 0xA]);
+        assert!(read_image(b"1e 2f", ImageFormat::Logisim).is_err());
+    }
+
+    #[test]
+    fn text_accepts_prefixes_and_comments() {
+        assert_eq!(read_image(b"0x1E 2f # LDA 14, ADD 15\n50\n", ImageFormat::Hex).unwrap(), vec![0x1E, 0x2F, 0x50]);
+        assert_eq!(read_image(b"0b0001_1110\n00101111\n", ImageFormat::Binary).unwrap(), vec![0x1E, 0x2F]);
+        assert_eq!(read_image(b"1G", ImageFormat::Hex).unwrap_err(), "invalid word '1G'");
+    }
+
+    #[test]
+    fn validate_summarises_a_program() {
+        let report = validate(&PROGRAM);
+        assert_eq!(report.used, 16);
+        // LDA, ADD, OUT and HLT, the data words are never reached
+        assert_eq!(report.instructions, 4);
+        assert!(report.findings.is_empty());
+        assert_eq!(report.summary(), "16 of 16 words used, 4 instructions decoded");
+    }
+
+    #[test]
+    fn validate_reports_oversize_and_wide_words() {
+        let mut memory = PROGRAM.to_vec();
+        memory.extend([0; 4]);
+        assert_eq!(validate(&memory).findings, vec![Finding::Padding { words: 4 }]);
+        memory.push(0x1E);
+        memory[14] = 0x11C;
+        let report = validate(&memory);
+        assert_eq!(report.findings, vec![Finding::Truncated { words: 5 }, Finding::Wide { address: 14, value: 0x11C }]);
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn validate_warns_of_reachable_undefined_opcodes() {
+        // JC 3 over an undefined opcode, the data at 15 has one too but is never run
+        let memory = [0x83, 0xA0, 0xF0, 0xB0, 0xF0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xE9];
+        let report = validate(&memory);
+        assert_eq!(report.findings, vec![
+            Finding::UndefinedOpcode { address: 1, opcode: 0xA },
+            Finding::UndefinedOpcode { address: 3, opcode: 0xB },
+        ]);
+        assert!(!report.has_errors());
+        assert_eq!(report.instructions, 5);
+    }
+
+    #[test]
+    fn bin_files_are_always_raw() {
+        assert_eq!(ImageFormat::detect("program.bin", b":1E"), ImageFormat::Raw);
+        assert_eq!(ImageFormat::detect("program", b":1E"), ImageFormat::IntelHex);
+        assert_eq!(ImageFormat::detect("program", &[0x1E, 0xFF]), ImageFormat::Raw);
+        assert_eq!(ImageFormat::from_extension("dump.HEX"), Some(ImageFormat::IntelHex));
+        assert_eq!(ImageFormat::from_extension("dump"), None);
+    }
+
+    #[test]
+    fn save_format_is_checked_up_front() {
+        assert_eq!(save_format("out.hex", None), Ok(ImageFormat::IntelHex));
+        assert_eq!(save_format("out.dat", Some(ImageFormat::Hex)), Ok(ImageFormat::Hex));
+        assert!(save_format("out.dat", None).is_err());
+        assert!(save_format("missing/directory/out.bin", None).is_err());
+        assert_eq!("hex-text".parse(), Ok(ImageFormat::Hex));
+    }
+}
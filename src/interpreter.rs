@@ -0,0 +1,239 @@
+use std::fmt;
+
+use crate::{alu::{add, Flags}, bitvecutils::{convert_ramdump_to_words, get_bits, mask, Word}, computer::{Computer, OutputRecord, StepResult}, config::{OPCODE_SIZE, RAM_SIZE, WORD_SIZE}, control::{decoder::{Decoder, OpCode}, sequencer::Sequencer}, pc::ADDRESS_SIZE};
+
+// Registers, flags and RAM visible to a program, as left by the last instruction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchState {
+    pub pc: Word,
+    pub mar: Word,
+    pub ir: Word,
+    pub a: Word,
+    pub b: Word,
+    pub out: Word,
+    pub flags: Flags,
+    pub memory: Vec<Word>,
+    pub cycles: usize,
+    pub halted: bool,
+}
+
+impl ArchState {
+    pub fn of(computer: &Computer) -> Self {
+        Self {
+            pc: computer.pc.read(),
+            mar: computer.ram.mar.read(),
+            ir: computer.ir.read(),
+            a: computer.reg_a.read(),
+            b: computer.reg_b.read(),
+            out: computer.reg_out.read(),
+            flags: computer.alu.flags,
+            memory: computer.ram.memory.clone(),
+            cycles: computer.cycles,
+            halted: computer.is_halted(),
+        }
+    }
+
+    /// One "name: self != other" entry per field that differs.
+    pub fn diff(&self, other: &ArchState) -> Vec<String> {
+        let mut differences = Vec::new();
+        let mut compare = |name: &str, left: String, right: String| {
+            if left != right {
+                differences.push(format!("{}: {} != {}", name, left, right));
+            }
+        };
+        compare("pc", self.pc.to_string(), other.pc.to_string());
+        compare("mar", self.mar.to_string(), other.mar.to_string());
+        compare("ir", self.ir.to_string(), other.ir.to_string());
+        compare("a", self.a.to_string(), other.a.to_string());
+        compare("b", self.b.to_string(), other.b.to_string());
+        compare("out", self.out.to_string(), other.out.to_string());
+        compare("flags", format!("{:?}", self.flags), format!("{:?}", other.flags));
+        compare("cycles", self.cycles.to_string(), other.cycles.to_string());
+        compare("halted", self.halted.to_string(), other.halted.to_string());
+        for (address, (left, right)) in self.memory.iter().zip(&other.memory).enumerate() {
+            compare(&format!("ram[{}]", address), left.to_string(), right.to_string());
+        }
+        differences
+    }
+}
+
+// Runs whole instructions from the opcode table, without control lines or a clock.
+// Registers, RAM, output log and cycle counts match the microcoded Computer after every instruction.
+pub struct Interpreter {
+    decoder: Decoder,
+    fetch_cycles: usize,
+    // Microcode steps of each code, indexed by carry and zero
+    step_counts: Vec<[usize; 4]>,
+    state: ArchState,
+    output_log: Vec<OutputRecord>,
+}
+
+impl Interpreter {
+    pub fn new(ramdump: Vec<u8>) -> Self {
+        let decoder = Decoder::new();
+        let step_counts = (0..1 << OPCODE_SIZE)
+            .map(|code| {
+                let mut counts = [0; 4];
+                if let Some(opcode) = decoder.decode_opcode(code) {
+                    for (index, count) in counts.iter_mut().enumerate() {
+                        let flags = Flags { carry: index & 1 == 1, zero: index & 2 == 2 };
+                        *count = decoder.get_microcode(opcode, flags).len();
+                    }
+                }
+                counts
+            })
+            .collect();
+        Self {
+            decoder,
+            fetch_cycles: Sequencer::new().get_fetch_microcode().len(),
+            step_counts,
+            state: ArchState {
+                pc: 0,
+                mar: 0,
+                ir: 0,
+                a: 0,
+                b: 0,
+                out: 0,
+                flags: Flags::default(),
+                memory: convert_ramdump_to_words(&ramdump, WORD_SIZE, RAM_SIZE),
+                cycles: 0,
+                halted: false,
+            },
+            output_log: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> &ArchState {
+        &self.state
+    }
+
+    /// Every value latched into the Output Register, oldest first.
+    pub fn outputs(&self) -> impl DoubleEndedIterator<Item = &OutputRecord> + ExactSizeIterator {
+        self.output_log.iter()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.state.halted
+    }
+
+    /// Runs the next instruction, fetch included.
+    pub fn step(&mut self) -> StepResult {
+        let state = &mut self.state;
+        if state.halted {
+            return StepResult { halted: true, ..StepResult::default() };
+        }
+        let start = state.cycles;
+        let word = state.memory[state.pc as usize];
+        let operand = get_bits(word, 0, ADDRESS_SIZE);
+        state.mar = state.pc;
+        state.ir = get_bits(word, ADDRESS_SIZE, OPCODE_SIZE);
+        state.pc = (state.pc + 1) & mask(ADDRESS_SIZE);
+        let flag_index = state.flags.carry as usize | (state.flags.zero as usize) << 1;
+        let steps = self.step_counts[state.ir as usize][flag_index];
+        // Cycle of the first step after the fetch
+        let execute = start + self.fetch_cycles;
+        state.cycles = execute + steps;
+
+        match self.decoder.decode_opcode(state.ir) {
+            Some(OpCode::LDA) => {
+                state.mar = operand;
+                state.a = state.memory[operand as usize];
+            }
+            Some(opcode @ (OpCode::ADD | OpCode::SUB)) => {
+                state.mar = operand;
+                state.b = state.memory[operand as usize];
+                let result = add(state.a, state.b, opcode == OpCode::SUB);
+                state.a = result.value;
+                state.flags = Flags { carry: result.carry, zero: result.zero };
+            }
+            Some(OpCode::OUT) => {
+                state.out = state.a;
+                self.output_log.push(OutputRecord { cycle: execute, value: state.a });
+            }
+            Some(OpCode::STA) => {
+                state.mar = operand;
+                state.memory[operand as usize] = state.a;
+            }
+            Some(OpCode::JMP) => state.pc = operand,
+            Some(OpCode::JC) if state.flags.carry => state.pc = operand,
+            Some(OpCode::JZ) if state.flags.zero => state.pc = operand,
+            Some(OpCode::HLT) => state.halted = true,
+            // NOP, MUL without microcode, jumps not taken and undefined opcodes
+            _ => {}
+        }
+        StepResult {
+            cycles: state.cycles - start,
+            halted: state.halted,
+            ..StepResult::default()
+        }
+    }
+
+    /// Runs instructions until the machine halts or at least `max_cycles` have run.
+    pub fn run(&mut self, max_cycles: usize) -> StepResult {
+        let mut result = StepResult::default();
+        while result.cycles < max_cycles && !result.halted {
+            let step = self.step();
+            result.cycles += step.cycles;
+            result.halted = step.halted;
+        }
+        result
+    }
+}
+
+// First instruction after which the microcoded and the instruction-level engines disagree
+#[derive(Debug)]
+pub struct Divergence {
+    pub instruction: usize,
+    pub address: Word,
+    pub differences: Vec<String>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "instruction {} at address {} diverged (microcode != interpreter): {}", self.instruction, self.address, self.differences.join(", "))
+    }
+}
+
+// Both engines loaded with the same program, compared after every instruction
+pub struct CrossCheck {
+    pub computer: Computer,
+    pub interpreter: Interpreter,
+    instruction: usize,
+}
+
+impl CrossCheck {
+    pub fn new(ramdump: Vec<u8>) -> Self {
+        Self {
+            computer: Computer::new(ramdump.clone()),
+            interpreter: Interpreter::new(ramdump),
+            instruction: 0,
+        }
+    }
+
+    /// Runs one instruction on each engine, the machine state can be changed between calls.
+    pub fn step(&mut self) -> Result<StepResult, Divergence> {
+        let address = self.interpreter.state().pc;
+        let result = self.computer.step_instruction();
+        self.interpreter.step();
+        let mut differences = ArchState::of(&self.computer).diff(self.interpreter.state());
+        if self.computer.outputs().last() != self.interpreter.outputs().last() || self.computer.outputs().len() != self.interpreter.outputs().len() {
+            differences.push("output log".to_string());
+        }
+        if !differences.is_empty() {
+            return Err(Divergence { instruction: self.instruction, address, differences });
+        }
+        self.instruction += 1;
+        Ok(result)
+    }
+}
+
+/// Runs a program on both engines side by side, comparing them after every instruction
+/// until it halts or `max_cycles` have run.
+pub fn cross_check(ramdump: Vec<u8>, max_cycles: usize) -> Result<StepResult, Divergence> {
+    let mut check = CrossCheck::new(ramdump);
+    let mut result = StepResult::default();
+    while result.cycles < max_cycles && !result.halted {
+        result.merge(check.step()?);
+    }
+    Ok(result)
+}
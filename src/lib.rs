@@ -0,0 +1,21 @@
+
+//! SAP-1 computer emulator, usable headless or through the terminal interface of the `tui` feature.
+
+pub mod memory;
+pub mod bitvecutils;
+pub mod config;
+pub mod control;
+pub mod bus;
+pub mod clock;
+pub mod pc;
+pub mod alu;
+pub mod link;
+pub mod computer;
+pub mod interpreter;
+pub mod examples;
+pub mod image;
+pub mod vcd;
+#[cfg(feature = "tui")]
+pub mod display;
+#[cfg(feature = "tui")]
+pub mod events;
@@ -1,46 +1,132 @@
-use std::collections::HashMap;
+use std::ops::Index;
 
-use crate::control::control::ControlLine;
+use crate::control::line::ControlLine;
 
 pub struct Link {
     pub control: ControlLine,
-    endpoints: HashMap<String, bool>,
-    callbacks: Vec<Box<dyn Fn()>>
+    endpoints: Vec<(String, bool)>,
+    // OR of the endpoints, kept up to date as they are driven
+    state: bool,
+    // Level held regardless of the endpoints, like a line pulled to a rail
+    forced: Option<bool>,
 }
 
 impl Link {
     pub fn new(control: ControlLine) -> Self {
         Self {
             control,
-            endpoints: HashMap::new(),
-            callbacks: Vec::new()
+            endpoints: Vec::new(),
+            state: false,
+            forced: None,
         }
     }
 
-    pub fn add_endpoint(&mut self, ep_indentifier: String) {
-        self.endpoints.insert(ep_indentifier, false);
+    fn update_state(&mut self) {
+        self.state = self.forced.unwrap_or_else(|| self.endpoints.iter().any(|(_, value)| *value));
     }
 
-    pub fn set_endpoint(&mut self, ep_indentifier: String, state: bool) {
-        if let Some(value) = self.endpoints.get_mut(&ep_indentifier) {
+    /// Connects a driver to the line, released, and returns the index it is driven by.
+    pub fn add_endpoint(&mut self, ep_indentifier: String) -> usize {
+        let index = match self.endpoints.iter().position(|(name, _)| *name == ep_indentifier) {
+            Some(index) => index,
+            None => {
+                self.endpoints.push((ep_indentifier, false));
+                self.endpoints.len() - 1
+            }
+        };
+        self.set_endpoint(index, false);
+        index
+    }
+
+    pub fn set_endpoint(&mut self, endpoint: usize, state: bool) {
+        if let Some((_, value)) = self.endpoints.get_mut(endpoint) {
             *value = state;
         }
-        // Run callbacks if any
-        for cb in &self.callbacks {
-            cb();
-        }
+        self.update_state();
+    }
+
+    pub fn get_endpoint(&self, endpoint: usize) -> bool {
+        self.endpoints.get(endpoint).is_some_and(|(_, value)| *value)
+    }
+
+    /// Holds the line at a level whatever its endpoints drive, `None` hands it back to them.
+    pub fn force(&mut self, level: Option<bool>) {
+        self.forced = level;
+        self.update_state();
     }
 
-    pub fn add_callback(&mut self, callback: Box<dyn Fn()>) {
-        self.callbacks.push(callback);
+    pub fn get_forced(&self) -> Option<bool> {
+        self.forced
     }
 
     pub fn get_state(&self) -> bool {
-        for (_ep_id, val) in &self.endpoints {
-            if *val {
-                return true;
-            }
+        self.state
+    }
+}
+
+// Every control line of a machine, indexed by line, with their levels packed in one word
+pub struct ControlLinks {
+    links: Vec<Link>,
+    levels: u32,
+}
+
+impl Default for ControlLinks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ControlLinks {
+    pub fn new() -> Self {
+        Self {
+            links: ControlLine::all().into_iter().map(Link::new).collect(),
+            levels: 0,
         }
-        return false;
     }
-}
\ No newline at end of file
+
+    /// Asserted lines, bit `n` is the line at index `n` of `ControlLine::all`.
+    pub fn word(&self) -> u32 {
+        self.levels
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Link> {
+        self.links.iter()
+    }
+
+    /// Connects a driver to every line, the same index drives it on each of them.
+    pub fn add_endpoint(&mut self, ep_indentifier: &str) -> usize {
+        let mut index = 0;
+        for line in ControlLine::all() {
+            index = self.update(&line, |link| link.add_endpoint(ep_indentifier.to_string()));
+        }
+        index
+    }
+
+    pub fn set_endpoint(&mut self, line: &ControlLine, endpoint: usize, state: bool) {
+        self.update(line, |link| link.set_endpoint(endpoint, state));
+    }
+
+    pub fn force(&mut self, line: &ControlLine, level: Option<bool>) {
+        self.update(line, |link| link.force(level));
+    }
+
+    // Changes a link and mirrors its new level into the word
+    fn update<T>(&mut self, line: &ControlLine, change: impl FnOnce(&mut Link) -> T) -> T {
+        let link = &mut self.links[line.index()];
+        let result = change(link);
+        if link.get_state() {
+            self.levels |= line.bit();
+        } else {
+            self.levels &= !line.bit();
+        }
+        result
+    }
+}
+
+impl Index<&ControlLine> for ControlLinks {
+    type Output = Link;
+
+    fn index(&self, line: &ControlLine) -> &Link {
+        &self.links[line.index()]
+    }
+}
@@ -1,66 +1,419 @@
-use std::{cell::RefCell, env, fs::File, io::Read, rc::Rc, sync::mpsc};
-
-use clock::Clock;
-use computer::Computer;
-use config::CLOCK_FREQUENCY;
-use display::renderer::Renderer;
-use events::keyboard::handle_keyboard;
-
-mod display;
-mod memory;
-mod bitvecutils;
-mod config;
-mod control;
-mod bus;
-mod clock;
-mod pc;
-mod alu;
-mod link;
-mod events;
-mod computer;
+use std::{cell::RefCell, env, fs::{self, File}, io::BufWriter, rc::Rc, sync::mpsc::{self, TryRecvError}, time::{Duration, Instant}};
+
+use sapxemu::{bitvecutils::{convert_ramdump_to_words, convert_words_to_ramdump}, clock::{Clock, ClockMode}, computer::{Computer, RegisterId}, config::{CLOCK_FREQUENCY, FRAME_RATE, MAX_FREQUENCY, RAM_SIZE, WORD_SIZE}, examples::{self, EXAMPLES}, image::{self, ImageFormat}, interpreter::cross_check, vcd::VcdWriter};
+use sapxemu::display::{renderer::Renderer, theme::Theme, waveform::{Waveform, WAVEFORM_HISTORY}};
+use sapxemu::events::{bindings::{Action, KeyBindings}, input::{handle_input, Input}};
+use settings::Settings;
+
+mod settings;
+
+// Cycle budget of --cross-check, for programs that never halt
+const CROSS_CHECK_CYCLES: usize = 1_000_000;
+
+// Written by the dump_ram key in the working directory when no --dump file is given,
+// an existing file is only replaced if this session wrote it
+const DUMP_FILE: &str = "dump.bin";
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     let mut ramdump: Vec<u8> = Vec::new();
+    let mut binfile: Option<String> = None;
+    let mut example: Option<String> = None;
+    let mut format: Option<ImageFormat> = None;
+    let mut dumpfile: Option<String> = None;
+    let mut dump_format: Option<ImageFormat> = None;
+    let mut dump_registers = false;
+    let mut vcdfile: Option<String> = None;
+    let mut keysfile: Option<String> = None;
+    let mut configfile: Option<String> = None;
+    let mut themename: Option<String> = None;
+    let mut check = false;
 
-    if args.len() > 1 {
-        let binfile = &args[1];
-        match File::open(binfile) {
-            Ok(mut file) => {
-                if file.read_to_end(&mut ramdump).is_err() {
-                    eprintln!("Error loading bin file {} into RAM", binfile);
-                } else {
-                    println!("Loaded bin file {} into RAM", binfile);
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--vcd" => match args_iter.next() {
+                Some(path) => vcdfile = Some(path.clone()),
+                None => {
+                    eprintln!("Missing file path after --vcd");
+                    return;
+                }
+            },
+            "--keys" => match args_iter.next() {
+                Some(path) => keysfile = Some(path.clone()),
+                None => {
+                    eprintln!("Missing file path after --keys");
+                    return;
+                }
+            },
+            "--config" => match args_iter.next() {
+                Some(path) => configfile = Some(path.clone()),
+                None => {
+                    eprintln!("Missing file path after --config");
+                    return;
+                }
+            },
+            "--theme" => match args_iter.next() {
+                Some(name) => themename = Some(name.clone()),
+                None => {
+                    eprintln!("Missing theme name after --theme");
+                    return;
+                }
+            },
+            "--example" => match args_iter.next() {
+                Some(name) => example = Some(name.clone()),
+                None => {
+                    eprintln!("Missing example name after --example");
+                    return;
+                }
+            },
+            "--format" => match args_iter.next().map(|name| name.parse::<ImageFormat>()) {
+                Some(Ok(name)) => format = Some(name),
+                Some(Err(e)) => {
+                    eprintln!("Error selecting image format: {}", e);
+                    return;
+                }
+                None => {
+                    eprintln!("Missing image format after --format");
+                    return;
                 }
+            },
+            "--dump" => match args_iter.next() {
+                Some(path) => dumpfile = Some(path.clone()),
+                None => {
+                    eprintln!("Missing file path after --dump");
+                    return;
+                }
+            },
+            "--dump-format" => match args_iter.next().map(|name| name.parse::<ImageFormat>()) {
+                Some(Ok(name)) => dump_format = Some(name),
+                Some(Err(e)) => {
+                    eprintln!("Error selecting dump format: {}", e);
+                    return;
+                }
+                None => {
+                    eprintln!("Missing image format after --dump-format");
+                    return;
+                }
+            },
+            "--dump-registers" => dump_registers = true,
+            "--cross-check" => check = true,
+            _ => binfile = Some(arg.clone()),
+        }
+    }
+
+    // A dump that can't be written is reported now rather than after the whole session
+    if let Some(path) = &dumpfile {
+        if let Err(e) = image::save_format(path, dump_format) {
+            eprintln!("Error with --dump: {}", e);
+            return;
+        }
+    }
+
+    let loaded = if let Some(name) = &example {
+        match examples::find(name) {
+            Some(example) => Some((format!("example {}", example.name), convert_ramdump_to_words(example.image, WORD_SIZE, RAM_SIZE))),
+            None => {
+                let names = EXAMPLES.iter().map(|example| example.name).collect::<Vec<_>>();
+                eprintln!("Unknown example '{}', available examples: {}", name, names.join(", "));
+                return;
             }
+        }
+    } else if let Some(binfile) = &binfile {
+        match image::load(binfile, format) {
+            Ok((format, memory)) => Some((format!("{} image {}", format, binfile), memory)),
             Err(e) => {
-                eprintln!("Error reading file '{}': {}", binfile, e);
+                eprintln!("Error loading image {}", e);
                 return;
             }
         }
     } else {
-        println!("No bin file provided. Running with empty RAM.");
+        None
+    };
+
+    match loaded {
+        Some((name, memory)) => {
+            // Report problems before anything runs
+            let report = image::validate(&memory);
+            for finding in &report.findings {
+                let severity = if finding.is_error() { "Error" } else { "Warning" };
+                eprintln!("{}: {}", severity, finding);
+            }
+            if report.has_errors() {
+                eprintln!("Not loading {}", name);
+                return;
+            }
+            ramdump = convert_words_to_ramdump(&memory, WORD_SIZE);
+            println!("Loaded {} into RAM, {}", name, report.summary());
+        }
+        None => println!("No bin file provided. Running with empty RAM."),
+    }
+
+    if check {
+        // Headless run of both engines, for scripts
+        match cross_check(ramdump, CROSS_CHECK_CYCLES) {
+            Ok(result) if result.halted => println!("Both engines agree, halted after {} cycles", result.cycles),
+            Ok(result) => println!("Both engines agree over {} cycles, HLT not reached", result.cycles),
+            Err(divergence) => {
+                eprintln!("{}", divergence);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let bindings = match &keysfile {
+        Some(path) => match KeyBindings::load(path) {
+            Ok(bindings) => bindings,
+            Err(e) => {
+                eprintln!("Error loading key bindings {}", e);
+                return;
+            }
+        },
+        None => KeyBindings::new(),
+    };
+
+    let settings = match &configfile {
+        Some(path) => match Settings::load(path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("Error loading config {}", e);
+                return;
+            }
+        },
+        None => Settings::new(),
+    };
+
+    let theme = match themename.or(settings.theme) {
+        Some(name) => match name.parse::<Theme>() {
+            Ok(theme) => theme,
+            Err(e) => {
+                eprintln!("Error selecting theme: {}", e);
+                return;
+            }
+        },
+        None => Theme::default_theme(),
+    };
+
+    let mut vcd = match &vcdfile {
+        Some(path) => match File::create(path) {
+            Ok(file) => Some(VcdWriter::new(BufWriter::new(file), CLOCK_FREQUENCY)),
+            Err(e) => {
+                eprintln!("Error creating VCD file '{}': {}", path, e);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    // Panic reports, printed once the terminal is restored
+    if let Err(e) = color_eyre::install() {
+        eprintln!("Error installing the panic handler: {}", e);
     }
 
     let clock = Rc::new(RefCell::new(Clock::new(CLOCK_FREQUENCY)));
     let mut computer = Computer::new(ramdump);
+    let mut snapshot = None;
 
-    let renderer = Rc::new(RefCell::new(Renderer::new()));
+    let mut waveform = Waveform::new(WAVEFORM_HISTORY);
+    let renderer = Rc::new(RefCell::new(Renderer::new(bindings.describe(), theme)));
 
     let (tx, rx) = mpsc::channel();
     clock.borrow_mut().start(tx);
 
-    loop {
-        handle_keyboard(&renderer, &computer.control_links).unwrap();
-        match rx.recv() {
-            Ok(clock_state) => {
-                computer.on_clock_high(clock_state);
-                renderer.borrow_mut().draw(&clock, &computer);
+    // Keyboard, clock edges and redraws are multiplexed so the interface keeps its frame rate at any clock speed
+    let frame_interval = Duration::from_secs(1) / FRAME_RATE;
+    let mut next_frame = Instant::now();
+    let mut dirty = true;
+    let mut halt_dumped = false;
+    let mut default_dump_written = false;
+    let mut input_error = None;
+    'main: loop {
+        // Terminal events, waiting at most until the next frame is due
+        let timeout = next_frame.saturating_duration_since(Instant::now());
+        let input = match handle_input(&renderer, &mut computer, &bindings, timeout) {
+            Ok(input) => input,
+            Err(e) => {
+                // Reported once the terminal is restored
+                input_error = Some(e);
+                break 'main;
             }
-            Err(_) => {
-                // Clock stopped
-                break;
+        };
+        if let Some(Input::Action(action)) = input {
+            match action {
+                Action::Quit => break 'main,
+                Action::Pause => clock.borrow_mut().toggle_pause(),
+                Action::Step if clock.borrow().get_mode() == ClockMode::Paused => {
+                    // Finish the current cycle, then run a whole new one
+                    if clock.borrow().get_state() {
+                        on_clock_edge(false, &clock, &mut computer, &mut waveform, &mut vcd);
+                    }
+                    on_clock_edge(true, &clock, &mut computer, &mut waveform, &mut vcd);
+                    on_clock_edge(false, &clock, &mut computer, &mut waveform, &mut vcd);
+                }
+                Action::Reset => {
+                    computer.reset();
+                    clock.borrow_mut().reset();
+                }
+                Action::SpeedUp | Action::SpeedDown => {
+                    let frequency = clock.borrow().get_frequency();
+                    let frequency = if action == Action::SpeedUp {
+                        (frequency * 2).min(MAX_FREQUENCY)
+                    } else {
+                        frequency / 2
+                    };
+                    clock.borrow_mut().set_frequency(frequency);
+                    if let Some(writer) = &mut vcd {
+                        writer.set_frequency(clock.borrow().get_frequency());
+                    }
+                }
+                Action::ManualMode => {
+                    computer.toggle_manual_mode();
+                    // Lines are set up by hand between single steps
+                    if computer.is_manual() && clock.borrow().get_mode() == ClockMode::Running {
+                        clock.borrow_mut().toggle_pause();
+                    }
+                }
+                Action::DumpRam => {
+                    let notice = match &dumpfile {
+                        Some(path) => dump_notice(dump(&computer, path, dump_format, dump_registers)),
+                        None => {
+                            let path = env::current_dir().map(|dir| dir.join(DUMP_FILE)).unwrap_or(DUMP_FILE.into());
+                            if path.exists() && !default_dump_written {
+                                (format!("Not overwriting {}, pass --dump <file> to choose where RAM is saved", path.display()), true)
+                            } else {
+                                let result = dump(&computer, &path.to_string_lossy(), dump_format, dump_registers);
+                                default_dump_written |= result.is_ok();
+                                dump_notice(result)
+                            }
+                        }
+                    };
+                    renderer.borrow_mut().view.notice = Some(notice);
+                }
+                Action::SaveState => snapshot = Some(computer.snapshot()),
+                Action::LoadState => {
+                    if let Some(state) = &snapshot {
+                        computer.restore(state);
+                    }
+                }
+                _ => {}
             }
         }
+        if let Some(Input::LoadExample(example)) = input {
+            computer.load_program(example.image);
+            clock.borrow_mut().reset();
+        }
+        if input.is_some() {
+            dirty = true;
+        }
+        // Clock edges received meanwhile, bounded so a fast clock can't hold back the interface
+        let budget = Instant::now() + frame_interval;
+        while Instant::now() < budget {
+            match rx.try_recv() {
+                Ok(clock_state) => {
+                    // Edges sent while paused are dropped, so the first one after a resume can repeat
+                    // the current level and must not run the same half-cycle twice
+                    if clock.borrow().get_mode() != ClockMode::Running || clock_state == clock.borrow().get_state() {
+                        continue;
+                    }
+                    on_clock_edge(clock_state, &clock, &mut computer, &mut waveform, &mut vcd);
+                    if !clock_state && computer.at_breakpoint() {
+                        clock.borrow_mut().toggle_pause();
+                    }
+                    dirty = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    // Clock stopped
+                    break 'main;
+                }
+            }
+        }
+        // Results of the program, written once each time it halts
+        if let Some(path) = dumpfile.as_deref().filter(|_| computer.is_halted() && !halt_dumped) {
+            renderer.borrow_mut().view.notice = Some(dump_notice(dump(&computer, path, dump_format, dump_registers)));
+            dirty = true;
+        }
+        halt_dumped = computer.is_halted();
+        if Instant::now() >= next_frame {
+            if dirty {
+                renderer.borrow_mut().draw(&clock, &computer, &waveform);
+                dirty = false;
+            }
+            next_frame = Instant::now() + frame_interval;
+        }
+    }
+    // Join the clock thread while its receiver still exists, the terminal is restored when the renderer drops
+    clock.borrow_mut().stop();
+    // Restore the terminal before reporting the final dump
+    drop(renderer);
+    if let Some(e) = &input_error {
+        eprintln!("Error reading terminal input: {}", e);
+    }
+    if let Some(path) = &dumpfile {
+        match dump(&computer, path, dump_format, dump_registers) {
+            Ok(message) => println!("{}", message),
+            Err(e) => eprintln!("Error saving RAM {}", e),
+        }
+    }
+    if input_error.is_some() {
+        std::process::exit(1);
+    }
+}
+
+/// Writes RAM to `path`, and the registers to `path.registers` when asked.
+fn dump(computer: &Computer, path: &str, format: Option<ImageFormat>, registers: bool) -> Result<String, String> {
+    let format = image::save(path, &computer.ram.memory, format)?;
+    if registers {
+        let mut text = String::new();
+        for (name, register) in [
+            ("pc", RegisterId::PC),
+            ("mar", RegisterId::MAR),
+            ("ir", RegisterId::IR),
+            ("reg_a", RegisterId::A),
+            ("reg_b", RegisterId::B),
+            ("reg_out", RegisterId::OUT),
+        ] {
+            text.push_str(&format!("{} = 0x{:X}\n", name, computer.read_register(register)));
+        }
+        let flags = computer.alu.flags;
+        text.push_str(&format!("carry = {}\nzero = {}\n", flags.carry as u8, flags.zero as u8));
+        let registers_path = format!("{}.registers", path);
+        fs::write(&registers_path, text).map_err(|e| format!("{}: {}", registers_path, e))?;
+    }
+    let full_path = fs::canonicalize(path).map(|full| full.display().to_string()).unwrap_or(path.to_string());
+    Ok(format!("Saved RAM to {} as {}", full_path, format))
+}
+
+fn dump_notice(result: Result<String, String>) -> (String, bool) {
+    match result {
+        Ok(message) => (message, false),
+        Err(e) => (format!("Error saving RAM {}", e), true),
+    }
+}
+
+fn on_clock_edge(
+    clock_state: bool,
+    clock: &Rc<RefCell<Clock>>,
+    computer: &mut Computer,
+    waveform: &mut Waveform,
+    vcd: &mut Option<VcdWriter<BufWriter<File>>>,
+) {
+    if clock.borrow().get_mode() == ClockMode::Halted {
+        return;
+    }
+    clock.borrow_mut().on_edge(clock_state);
+    computer.on_clock_high(clock_state);
+    if clock_state && computer.is_halted() {
+        clock.borrow_mut().halt();
+    } else if !clock_state && !computer.is_manual() && computer.controller.sequencer.get_step() == 0 {
+        clock.borrow_mut().count_instruction();
+    }
+    if let Some(writer) = vcd {
+        if writer.sample(computer, clock_state).is_err() {
+            // Stop tracing rather than interrupting the emulation
+            *vcd = None;
+        }
     }
-}
\ No newline at end of file
+    waveform.record(computer, clock_state);
+}
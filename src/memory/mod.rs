@@ -1,2 +1,2 @@
-pub mod memory;
+pub mod ram;
 pub mod register;
\ No newline at end of file
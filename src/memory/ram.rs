@@ -0,0 +1,40 @@
+use crate::{bitvecutils::{convert_ramdump_to_words, mask, Word}, bus::{Bus, BusSelector}, config::{RAM_SIZE, WORD_SIZE}, memory::register::RORegister, pc::ADDRESS_SIZE};
+
+// RAM, addressed by its own Memory Address Register
+pub struct RAM {
+    pub memory: Vec<Word>,
+    pub mar: RORegister,
+}
+
+impl RAM {
+    pub fn new(ramdump: &[u8]) -> Self {
+        Self {
+            memory: convert_ramdump_to_words(ramdump, WORD_SIZE, RAM_SIZE),
+            mar: RORegister::new("Mem. Address Reg.".to_string(), ADDRESS_SIZE, BusSelector::LSB),
+        }
+    }
+
+    pub fn write_word(&mut self, address: usize, value: Word) {
+        if let Some(word) = self.memory.get_mut(address) {
+            *word = value & mask(WORD_SIZE);
+        }
+    }
+
+    pub fn toggle_bit(&mut self, address: usize, bit: usize) {
+        if let Some(word) = self.memory.get_mut(address) {
+            if bit < WORD_SIZE {
+                *word ^= 1 << bit;
+            }
+        }
+    }
+
+    /// Puts the addressed cell on the bus.
+    pub fn drive_bus(&self, bus: &mut Bus) {
+        bus.write(self.memory[self.mar.read() as usize], WORD_SIZE);
+    }
+
+    /// Stores the bus into the addressed cell.
+    pub fn latch(&mut self, bus: &Bus) {
+        self.write_word(self.mar.read() as usize, bus.read());
+    }
+}
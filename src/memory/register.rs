@@ -1,80 +1,70 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
-
-use bit_vec::BitVec;
-
-use crate::{bus::{Bus, BusSelector}, clock::ClockDriven, control::control::ControlLine, link::Link};
+use crate::{bitvecutils::Word, bus::{Bus, BusSelector}};
 
 // RORegister
 pub struct RORegister {
-    pub control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,
     pub name: String,
-    bus: Rc<RefCell<Bus>>,
     bus_selector: BusSelector,
-    regin_ctrl: ControlLine,
-    pub data: BitVec,
+    pub data: Word,
+    width: usize,
 }
 
 impl RORegister {
-    pub fn new(name: String, size: usize, bus: Rc<RefCell<Bus>>, bus_selector: BusSelector, regin_ctrl: ControlLine, control_links: HashMap<ControlLine, Rc<RefCell<Link>>>) -> Self {
+    pub fn new(name: String, size: usize, bus_selector: BusSelector) -> Self {
         Self {
-            control_links,
             name,
-            bus,
             bus_selector,
-            regin_ctrl,
-            data: BitVec::from_elem(size, false)
+            data: 0,
+            width: size,
         }
     }
 
-    pub fn read(&self) -> BitVec {
-        return self.data.clone();
+    pub fn read(&self) -> Word {
+        self.data
     }
-}
 
-impl ClockDriven for RORegister {
-    fn on_clock_high(&mut self) {
-        if self.control_links[&self.regin_ctrl].borrow().get_state() {
-            self.data = self.bus.borrow().read_part(self.data.len(), self.bus_selector);
-        }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Loads its part of the bus, on a rising edge with its in line asserted.
+    pub fn latch(&mut self, bus: &Bus) {
+        self.data = bus.read_part(self.width, self.bus_selector);
     }
 }
 
 // RWRegister
 pub struct RWRegister {
-    pub control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,
     pub name: String,
-    regin_ctrl: ControlLine,
-    regout_ctrl: ControlLine,
-    bus: Rc<RefCell<Bus>>,
     bus_selector: BusSelector,
-    pub data: BitVec,
+    pub data: Word,
+    width: usize,
 }
 
 impl RWRegister {
-    pub fn new(name: String, size: usize, bus: Rc<RefCell<Bus>>, bus_selector: BusSelector, regin_ctrl: ControlLine, regout_ctrl: ControlLine, control_links: HashMap<ControlLine, Rc<RefCell<Link>>>) -> Self {
+    pub fn new(name: String, size: usize, bus_selector: BusSelector) -> Self {
         Self {
-            control_links,
             name,
-            bus,
             bus_selector,
-            regin_ctrl,
-            regout_ctrl,
-            data: BitVec::from_elem(size, false)
+            data: 0,
+            width: size,
         }
     }
 
-    pub fn read(&self) -> BitVec {
-        return self.data.clone();
+    pub fn read(&self) -> Word {
+        self.data
     }
-}
 
-impl ClockDriven for RWRegister {
-    fn on_clock_high(&mut self) {
-        if self.control_links[&self.regin_ctrl].borrow().get_state() {
-            self.data = self.bus.borrow().read_part(self.data.len(), self.bus_selector);
-        }
-        if self.control_links[&self.regout_ctrl].borrow().get_state() {
-            self.bus.borrow_mut().write(&self.data);
-        }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Puts the value on the bus, every module drives before any of them latches.
+    pub fn drive_bus(&self, bus: &mut Bus) {
+        bus.write(self.data, self.width);
     }
-}
\ No newline at end of file
+
+    /// Loads its part of the bus, on a rising edge with its in line asserted.
+    pub fn latch(&mut self, bus: &Bus) {
+        self.data = bus.read_part(self.width, self.bus_selector);
+    }
+}
@@ -1,40 +1,76 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use crate::{bitvecutils::{mask, Word}, bus::Bus, config::{OPCODE_SIZE, WORD_SIZE}};
 
-use bit_vec::BitVec;
-
-use crate::{bitvecutils::increment_bitset, bus::Bus, clock::ClockDriven, config::{OPCODE_SIZE, WORD_SIZE}, control::control::ControlLine, link::Link};
+// Width of an address, the low bits of an instruction word
+pub const ADDRESS_SIZE: usize = WORD_SIZE - OPCODE_SIZE;
 
 // Program Counter
 pub struct ProgramCounter {
-    control_links: HashMap<ControlLine, Rc<RefCell<Link>>>, 
-    bus: Rc<RefCell<Bus>>,
-    pub address: BitVec,
+    pub address: Word,
 }
 
-impl<'a> ProgramCounter {
-    pub fn new(control_links: HashMap<ControlLine, Rc<RefCell<Link>>>,  bus: Rc<RefCell<Bus>>) -> Self {
+impl Default for ProgramCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgramCounter {
+    pub fn new() -> Self {
         Self {
-            control_links,
-            bus,
-            address: BitVec::from_elem(WORD_SIZE - OPCODE_SIZE, false)
+            address: 0
         }
     }
 
-    pub fn read(&self) -> BitVec {
-        return self.address.clone();
+    pub fn read(&self) -> Word {
+        self.address
+    }
+
+    /// Puts the address on the bus, on CO.
+    pub fn drive_bus(&self, bus: &mut Bus) {
+        bus.write(self.address, ADDRESS_SIZE);
+    }
+
+    /// Loads the low bits of the bus, on J.
+    pub fn jump(&mut self, bus: &Bus) {
+        self.address = bus.read() & mask(ADDRESS_SIZE);
+    }
+
+    /// Counts up, on CE.
+    pub fn increment(&mut self) {
+        // Wraps around like the 4-bit counter chip
+        self.address = self.address.wrapping_add(1) & mask(ADDRESS_SIZE);
     }
 }
 
-impl ClockDriven for ProgramCounter {
-    fn on_clock_high(&mut self) {
-        if self.control_links[&ControlLine::CO].borrow().get_state() {
-            self.bus.borrow_mut().write(&self.address);
-        }
-        if self.control_links[&ControlLine::J].borrow().get_state() {
-            self.address = self.bus.borrow().read();
-        }
-        if self.control_links[&ControlLine::CE].borrow().get_state() {
-            increment_bitset(&mut self.address);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn increments_and_wraps() {
+        let mut pc = ProgramCounter::new();
+        pc.increment();
+        assert_eq!(pc.read(), 1);
+        pc.address = mask(ADDRESS_SIZE);
+        pc.increment();
+        assert_eq!(pc.read(), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn jumps_to_the_low_bits_of_the_bus() {
+        let mut pc = ProgramCounter::new();
+        let mut bus = Bus::new();
+        bus.write(0x7B, WORD_SIZE);
+        pc.jump(&bus);
+        assert_eq!(pc.read(), 0xB);
+    }
+
+    #[test]
+    fn drives_the_address_on_the_bus() {
+        let mut pc = ProgramCounter::new();
+        let mut bus = Bus::new();
+        pc.address = 0x9;
+        pc.drive_bus(&mut bus);
+        assert_eq!(bus.read(), 0x9);
+    }
+}
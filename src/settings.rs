@@ -0,0 +1,33 @@
+use std::fs;
+
+// Options of the configuration file, the command line takes precedence
+pub struct Settings {
+    pub theme: Option<String>,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            theme: None,
+        }
+    }
+
+    /// Loads "option = value" lines, '#' starts a comment.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut settings = Self::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (option, value) = line.split_once('=')
+                .ok_or(format!("{}:{}: expected 'option = value'", path, index + 1))?;
+            match option.trim() {
+                "theme" => settings.theme = Some(value.trim().to_string()),
+                other => return Err(format!("{}:{}: unknown option '{}'", path, index + 1, other)),
+            }
+        }
+        Ok(settings)
+    }
+}
@@ -0,0 +1,191 @@
+use std::io::{Result, Write};
+
+use crate::{bitvecutils::{BinaryDisplay, Word}, computer::{Computer, RegisterId}, config::WORD_SIZE, control::line::ControlLine};
+
+// Value Change Dump (IEEE 1364) waveform writer, readable by GTKWave
+struct VcdSignal {
+    name: String,
+    width: usize,
+    id: String,
+}
+
+fn signal_identifier(mut index: usize) -> String {
+    // VCD identifiers use the printable ASCII range '!'..='~'
+    let mut id = String::new();
+    loop {
+        id.push((b'!' + (index % 94) as u8) as char);
+        index /= 94;
+        if index == 0 {
+            break;
+        }
+        index -= 1;
+    }
+    id
+}
+
+fn format_value(width: usize, value: Word) -> String {
+    if width == 1 {
+        if value & 1 == 1 { "1".to_string() } else { "0".to_string() }
+    } else {
+        format!("b{} ", value.to_bin_string(width))
+    }
+}
+
+pub struct VcdWriter<W: Write> {
+    out: W,
+    half_period: u64, // Microseconds
+    time: u64,
+    signals: Vec<VcdSignal>,
+    last_values: Vec<String>,
+}
+
+impl<W: Write> VcdWriter<W> {
+    pub fn new(out: W, frequency: usize) -> Self {
+        Self {
+            out,
+            half_period: (500_000 / frequency.max(1)) as u64,
+            time: 0,
+            signals: Vec::new(),
+            last_values: Vec::new(),
+        }
+    }
+
+    /// Keeps timestamps in real time when the clock speed changes.
+    pub fn set_frequency(&mut self, frequency: usize) {
+        self.half_period = (500_000 / frequency.max(1)) as u64;
+    }
+
+    // Name, width and value of every signal
+    fn sample_values(computer: &Computer, clock_state: bool) -> Vec<(String, usize, Word)> {
+        let mut values = vec![("CLK".to_string(), 1, clock_state as Word)];
+        for line in ControlLine::all() {
+            if line == ControlLine::CLK {
+                continue;
+            }
+            let state = computer.control_links[&line].get_state();
+            values.push((line.to_string(), 1, state as Word));
+        }
+        let registers = [
+            ("pc", RegisterId::PC),
+            ("mar", RegisterId::MAR),
+            ("ir", RegisterId::IR),
+            ("reg_a", RegisterId::A),
+            ("reg_b", RegisterId::B),
+            ("reg_out", RegisterId::OUT),
+        ];
+        values.push(("bus".to_string(), WORD_SIZE, computer.bus.read()));
+        for (name, register) in registers {
+            values.push((name.to_string(), computer.register_width(register), computer.read_register(register)));
+        }
+        values
+    }
+
+    fn write_header(&mut self, values: &[(String, usize, Word)]) -> Result<()> {
+        writeln!(self.out, "$version sapxemu {} $end", env!("CARGO_PKG_VERSION"))?;
+        writeln!(self.out, "$timescale 1us $end")?;
+        writeln!(self.out, "$scope module sapxemu $end")?;
+        for (index, (name, width, _value)) in values.iter().enumerate() {
+            let signal = VcdSignal {
+                name: name.clone(),
+                width: *width,
+                id: signal_identifier(index),
+            };
+            if signal.width == 1 {
+                writeln!(self.out, "$var wire 1 {} {} $end", signal.id, signal.name)?;
+            } else {
+                writeln!(self.out, "$var wire {} {} {} [{}:0] $end", signal.width, signal.id, signal.name, signal.width - 1)?;
+            }
+            self.signals.push(signal);
+        }
+        writeln!(self.out, "$upscope $end")?;
+        writeln!(self.out, "$enddefinitions $end")?;
+        Ok(())
+    }
+
+    /// Records the machine state after a clock edge, emitting only the signals that changed.
+    pub fn sample(&mut self, computer: &Computer, clock_state: bool) -> Result<()> {
+        let values = Self::sample_values(computer, clock_state);
+        let first = self.signals.is_empty();
+        if first {
+            self.write_header(&values)?;
+        }
+
+        let mut changes = Vec::new();
+        for (index, (_name, _width, value)) in values.iter().enumerate() {
+            let signal = &self.signals[index];
+            let formatted = format_value(signal.width, *value);
+            if first || self.last_values[index] != formatted {
+                changes.push(format!("{}{}", formatted, signal.id));
+            }
+            if first {
+                self.last_values.push(formatted);
+            } else {
+                self.last_values[index] = formatted;
+            }
+        }
+
+        if !changes.is_empty() {
+            writeln!(self.out, "#{}", self.time)?;
+            if first {
+                writeln!(self.out, "$dumpvars")?;
+            }
+            for change in changes {
+                writeln!(self.out, "{}", change)?;
+            }
+            if first {
+                writeln!(self.out, "$end")?;
+            }
+        }
+        self.time += self.half_period;
+        Ok(())
+    }
+}
+
+impl<W: Write> Drop for VcdWriter<W> {
+    fn drop(&mut self) {
+        // Samples are buffered for the whole run, a failed flush cannot be reported from here
+        let _ = self.out.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(writer: &VcdWriter<Vec<u8>>) -> String {
+        String::from_utf8(writer.out.clone()).unwrap()
+    }
+
+    #[test]
+    fn header_declares_every_signal() {
+        let computer = Computer::new(vec![]);
+        let mut writer = VcdWriter::new(Vec::new(), 1);
+        writer.sample(&computer, false).unwrap();
+        let text = output(&writer);
+        assert!(text.starts_with("$version sapxemu"));
+        assert!(text.contains("$timescale 1us $end\n"));
+        assert!(text.contains("$var wire 1 ! CLK $end\n"));
+        assert!(text.contains(&format!("bus [{}:0] $end\n", WORD_SIZE - 1)));
+        assert!(text.contains("$enddefinitions $end\n#0\n$dumpvars\n0!\n"));
+        // One declaration and one initial value per signal
+        let declared = text.lines().filter(|line| line.starts_with("$var")).count();
+        assert_eq!(declared, writer.signals.len());
+        assert_eq!(declared, ControlLine::all().len() + 7);
+    }
+
+    #[test]
+    fn only_changed_values_are_written() {
+        let computer = Computer::new(vec![]);
+        let mut writer = VcdWriter::new(Vec::new(), 1);
+        writer.sample(&computer, false).unwrap();
+        let header = output(&writer).len();
+
+        writer.sample(&computer, true).unwrap();
+        assert_eq!(&output(&writer)[header..], "#500000\n1!\n");
+
+        // Nothing changed, not even a timestamp is written
+        let before = output(&writer).len();
+        writer.sample(&computer, true).unwrap();
+        assert_eq!(output(&writer).len(), before);
+    }
+}
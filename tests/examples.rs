@@ -0,0 +1,85 @@
+// Programs of the examples directory, checked against their source and run on both engines
+
+use sapxemu::{bitvecutils::{convert_ramdump_to_words, Word}, computer::Computer, config::{RAM_SIZE, WORD_SIZE}, examples::{assemble, find, EXAMPLES}, image::validate, interpreter::{cross_check, Interpreter}};
+
+const MAX_CYCLES: usize = 1_000_000;
+
+fn expected_outputs(name: &str) -> Vec<Word> {
+    match name {
+        "count_up" => (0..=255).collect(),
+        "count_down" => (0..=255).rev().collect(),
+        "fibonacci" => vec![1, 2, 3, 5, 8, 13, 21, 34, 55, 89, 144, 233],
+        "multiply" => vec![255],
+        "prime" => vec![97],
+        // One period and the turn at the bottom
+        "bounce" => (0..=255).chain((0..255).rev()).chain([1]).collect(),
+        _ => panic!("{}: no expected outputs", name),
+    }
+}
+
+fn run(image: &[u8], max_outputs: usize) -> Vec<Word> {
+    let mut computer = Computer::new(image.to_vec());
+    computer.run_until(|computer| computer.outputs().count() >= max_outputs, MAX_CYCLES);
+    computer.outputs().map(|record| record.value).collect()
+}
+
+#[test]
+fn binaries_match_their_source() {
+    for example in EXAMPLES {
+        let memory = assemble(example.source).unwrap_or_else(|e| panic!("{}: {}", example.name, e));
+        assert_eq!(example.image.len() * 8, RAM_SIZE * WORD_SIZE, "{}: image size", example.name);
+        assert_eq!(convert_ramdump_to_words(example.image, WORD_SIZE, RAM_SIZE), memory, "{}: image", example.name);
+        assert_eq!(validate(&memory).findings, vec![], "{}: findings", example.name);
+    }
+}
+
+#[test]
+fn examples_print_their_outputs() {
+    for example in EXAMPLES {
+        let expected = expected_outputs(example.name);
+        assert_eq!(run(example.image, expected.len()), expected, "{}: outputs", example.name);
+
+        let mut interpreter = Interpreter::new(example.image.to_vec());
+        while !interpreter.is_halted() && interpreter.outputs().count() < expected.len() {
+            interpreter.step();
+        }
+        let outputs: Vec<Word> = interpreter.outputs().map(|record| record.value).collect();
+        assert_eq!(outputs, expected, "{}: interpreter outputs", example.name);
+    }
+}
+
+#[test]
+fn engines_agree_on_every_example() {
+    for example in EXAMPLES {
+        if let Err(divergence) = cross_check(example.image.to_vec(), 20_000) {
+            panic!("{}: {}", example.name, divergence);
+        }
+    }
+}
+
+#[test]
+fn prime_finds_a_divisor() {
+    let mut image = find("prime").unwrap().image.to_vec();
+    for (n, output) in [(2, 2), (91, 0), (251, 251), (255, 0)] {
+        image[13] = n;
+        image[14] = n;
+        assert_eq!(run(&image, 2), vec![output], "N = {}", n);
+    }
+}
+
+#[test]
+fn output_log_keeps_every_value() {
+    let image = find("bounce").unwrap().image;
+    let mut computer = Computer::new(image.to_vec());
+    computer.run_until(|computer| computer.outputs().last().is_some_and(|record| record.cycle > 100_000), MAX_CYCLES);
+
+    // Two full bounces, each output up or down by one from the previous
+    let values: Vec<Word> = computer.outputs().map(|record| record.value).collect();
+    assert!(values.len() > 1024);
+    assert_eq!(values[..3], [0, 1, 2]);
+    assert!(values.windows(2).all(|pair| pair[0].abs_diff(pair[1]) == 1));
+    let snapshot = computer.snapshot();
+    computer.tick();
+    computer.restore(&snapshot);
+    assert!(computer.outputs().map(|record| record.value).eq(values));
+}
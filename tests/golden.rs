@@ -0,0 +1,220 @@
+// Golden programs run headless to HLT, on the microcoded Computer and on the Interpreter
+
+use sapxemu::{bitvecutils::Word, computer::{Computer, RegisterId}, control::line::ControlLine, examples::find, interpreter::{cross_check, CrossCheck, Interpreter}};
+
+struct Golden {
+    name: &'static str,
+    program: &'static [u8],
+    outputs: &'static [Word],
+    memory: [Word; 16],
+    cycles: usize,
+}
+
+const MAX_CYCLES: usize = 100_000;
+
+// Fibonacci and multiplication are the shipped example programs
+fn golden() -> Vec<Golden> {
+    vec![
+        Golden {
+            name: "add",
+            program: &[
+                0x1E, // 0: LDA 14
+                0x2F, // 1: ADD 15
+                0x50, // 2: OUT
+                0xF0, // 3: HLT
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                28, 14,
+            ],
+            outputs: &[42],
+            memory: [0x1E, 0x2F, 0x50, 0xF0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 28, 14],
+            cycles: 19,
+        },
+        Golden {
+            name: "subtract",
+            program: &[
+                0x1E, // 0: LDA 14
+                0x3F, // 1: SUB 15
+                0x50, // 2: OUT
+                0xF0, // 3: HLT
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                30, 12,
+            ],
+            outputs: &[18],
+            memory: [0x1E, 0x3F, 0x50, 0xF0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 30, 12],
+            cycles: 19,
+        },
+        Golden {
+            name: "counter loop",
+            program: &[
+                0x1F, // 0: LDA 15
+                0x50, // 1: OUT
+                0x3E, // 2: SUB 14
+                0x6F, // 3: STA 15
+                0x96, // 4: JZ 6
+                0x70, // 5: JMP 0
+                0x50, // 6: OUT
+                0xF0, // 7: HLT
+                0, 0, 0, 0, 0, 0,
+                1,    // 14: step
+                5,    // 15: counter
+            ],
+            outputs: &[5, 4, 3, 2, 1, 0],
+            memory: [0x1F, 0x50, 0x3E, 0x6F, 0x96, 0x70, 0x50, 0xF0, 0, 0, 0, 0, 0, 0, 1, 0],
+            cycles: 140,
+        },
+        Golden {
+            name: "fibonacci",
+            program: find("fibonacci").unwrap().image,
+            outputs: &[1, 2, 3, 5, 8, 13, 21, 34, 55, 89, 144, 233],
+            memory: [0x1E, 0x2F, 0x8A, 0x50, 0x6D, 0x1F, 0x6E, 0x1D, 0x6F, 0x70, 0xF0, 0, 0, 233, 144, 233],
+            cycles: 583,
+        },
+        Golden {
+            name: "multiply",
+            program: find("multiply").unwrap().image,
+            outputs: &[255],
+            memory: [0x1D, 0x2E, 0x6D, 0x1F, 0x3C, 0x6F, 0x98, 0x70, 0x1D, 0x50, 0xF0, 0, 1, 255, 17, 0],
+            cycles: 595,
+        },
+        Golden {
+            name: "self-modifying code",
+            program: &[
+                0x1B, // 0: LDA 11, the operand walks over the table
+                0x50, // 1: OUT
+                0x10, // 2: LDA 0
+                0x2F, // 3: ADD 15
+                0x60, // 4: STA 0
+                0x3A, // 5: SUB 10
+                0x98, // 6: JZ 8
+                0x70, // 7: JMP 0
+                0xF0, // 8: HLT
+                0,
+                0x1E, // 10: LDA 14, end of the table
+                7, 42, 99, // 11-13: table
+                0,
+                1,    // 15: one
+            ],
+            outputs: &[7, 42, 99],
+            memory: [0x1E, 0x50, 0x10, 0x2F, 0x60, 0x3A, 0x98, 0x70, 0xF0, 0, 0x1E, 7, 42, 99, 0, 1],
+            cycles: 115,
+        },
+    ]
+}
+
+#[test]
+fn microcode_matches_golden() {
+    for golden in golden() {
+        let mut computer = Computer::new(golden.program.to_vec());
+        let result = computer.run_until(|_| false, MAX_CYCLES);
+        assert!(result.halted, "{}: HLT not reached", golden.name);
+        assert!(!result.contention, "{}: bus contention", golden.name);
+        let outputs: Vec<Word> = computer.outputs().map(|record| record.value).collect();
+        assert_eq!(outputs, golden.outputs, "{}: outputs", golden.name);
+        assert_eq!(computer.ram.memory, golden.memory, "{}: final RAM", golden.name);
+        assert_eq!(result.cycles, golden.cycles, "{}: cycles", golden.name);
+    }
+}
+
+#[test]
+fn interpreter_matches_golden() {
+    for golden in golden() {
+        let mut interpreter = Interpreter::new(golden.program.to_vec());
+        let result = interpreter.run(MAX_CYCLES);
+        assert!(result.halted, "{}: HLT not reached", golden.name);
+        let outputs: Vec<Word> = interpreter.outputs().map(|record| record.value).collect();
+        assert_eq!(outputs, golden.outputs, "{}: outputs", golden.name);
+        assert_eq!(interpreter.state().memory, golden.memory, "{}: final RAM", golden.name);
+        assert_eq!(result.cycles, golden.cycles, "{}: cycles", golden.name);
+    }
+}
+
+#[test]
+fn engines_agree_on_every_instruction() {
+    for golden in golden() {
+        if let Err(divergence) = cross_check(golden.program.to_vec(), MAX_CYCLES) {
+            panic!("{}: {}", golden.name, divergence);
+        }
+    }
+}
+
+#[test]
+fn cross_check_reports_the_first_divergence() {
+    // add: LDA 14, ADD 15, OUT, HLT
+    let mut check = CrossCheck::new(golden()[0].program.to_vec());
+    assert!(check.step().is_ok());
+    // Only the microcoded machine sees the change before ADD
+    check.computer.write_register(RegisterId::A, 1);
+    let divergence = check.step().unwrap_err();
+    assert_eq!(divergence.instruction, 1);
+    assert_eq!(divergence.address, 1);
+    assert_eq!(divergence.differences, vec!["a: 15 != 42".to_string()]);
+
+    // RAM written behind the back of the interpreter is reported too
+    let mut check = CrossCheck::new(golden()[0].program.to_vec());
+    check.computer.ram.write_word(15, 4);
+    let divergence = check.step().unwrap_err();
+    assert_eq!(divergence.instruction, 0);
+    assert_eq!(divergence.differences, vec!["ram[15]: 4 != 14".to_string()]);
+}
+
+#[test]
+fn tick_stops_on_a_breakpoint_reached_by_its_first_half() {
+    let mut computer = Computer::new(golden()[0].program.to_vec());
+    computer.toggle_breakpoint(1);
+    // LDA 14 takes five cycles, stop with the clock high on the last one
+    for _ in 0..4 {
+        computer.tick();
+    }
+    computer.tick_half();
+    let result = computer.tick();
+    assert!(result.breakpoint);
+    assert_eq!(result.cycles, 0);
+    assert_eq!(computer.read_register(RegisterId::PC), 1);
+
+    // Running again leaves the breakpoint behind
+    let result = computer.run_until(|_| false, MAX_CYCLES);
+    assert!(result.halted);
+    assert!(!result.breakpoint);
+}
+
+#[test]
+fn forced_low_line_overrides_the_microcode() {
+    let golden = &golden()[0];
+    let mut computer = Computer::new(golden.program.to_vec());
+    // Released, forced high, then forced low
+    computer.toggle_user_control(&ControlLine::OI);
+    computer.toggle_user_control(&ControlLine::OI);
+    assert!(computer.run_until(|_| false, MAX_CYCLES).halted);
+    assert_eq!(computer.outputs().count(), 0);
+
+    computer.toggle_user_control(&ControlLine::OI);
+    computer.reset();
+    computer.run_until(|_| false, MAX_CYCLES);
+    let outputs: Vec<Word> = computer.outputs().map(|record| record.value).collect();
+    assert_eq!(outputs, golden.outputs);
+}
+
+#[test]
+fn reset_replays_the_same_run() {
+    let golden = &golden()[0];
+    let mut computer = Computer::new(golden.program.to_vec());
+    let first = computer.run_until(|_| false, MAX_CYCLES);
+    computer.reset();
+    let second = computer.run_until(|_| false, MAX_CYCLES);
+    assert_eq!(first, second);
+    let outputs: Vec<Word> = computer.outputs().map(|record| record.value).collect();
+    assert_eq!(outputs, golden.outputs);
+}
+
+#[test]
+fn snapshot_restores_a_run_in_progress() {
+    let golden = &golden()[3];
+    let mut computer = Computer::new(golden.program.to_vec());
+    computer.run_until(|_| false, 100);
+    let state = computer.snapshot();
+    let first = computer.run_until(|_| false, MAX_CYCLES);
+    computer.restore(&state);
+    let second = computer.run_until(|_| false, MAX_CYCLES);
+    assert_eq!(first, second);
+    assert_eq!(computer.ram.memory, golden.memory);
+}